@@ -1,103 +1,235 @@
-use std::{cell::Cell, marker::PhantomPinned, ops::Deref, pin::Pin, ptr};
+//! ピン留めしたノードを互いにつなぐ侵入型の双方向リスト
+//!
+//! # 不変条件
+//! - ノード`a`の`next`が非nullなら`a.next.prev == a`、`prev`が非nullなら`a.prev.next == a`が成り立つ。
+//!   デバッグビルドではつなぎ替えのたびにこれを確かめる。
+//! - `List`に属するノードは`list`にその番兵を指すポインタを持つ。
+//!   そのような鎖には同じリストのノードと番兵しか含まれず、つなぎ替えは`List`を介してのみ行われる。
+//! - ノードはドロップ時に自身を鎖から外すので、鎖がドロップ済みのノードを指すことはない。
+//!   要素をクロージャに貸している最中のノードがドロップされたときは、参照が宙に浮く前にプロセスを中断する。
+//!
+//! `Iterator`を実装したイテレータや`Cursor`・`CursorMut`は`List`にしかない。
+//! リストに属さない鎖では、あるノードを借用していても他のノードはいつでもドロップできるので、
+//! 借用の寿命で鎖全体を守ることはできない。そのような鎖は`Node::for_each_to_last`などでたどる。
+//!
+//! ノードはつながっている他のノードを`Cell`越しに書き換えるので、`Send`でも`Sync`でもない。
+//! ```compile_fail
+//! fn assert_send<T: Send>() {}
+//! assert_send::<linked_list::Node<i32>>();
+//! ```
+//! ```compile_fail
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<linked_list::Node<i32>>();
+//! ```
 
+use std::{cell::Cell, marker::PhantomPinned, ops::Deref, pin::Pin, process, ptr};
+
+mod list;
+mod sync;
+
+pub use list::{Cursor, CursorMut, Iter, List};
+pub use sync::{SyncIter, SyncList, SyncListGuard, SyncNode};
+
+// `Node`と`List`の番兵が共通して持つリンク
+struct Link {
+    next: Cell<*const Link>,
+    prev: Cell<*const Link>,
+    // 属している`List`の番兵。リストに属していなければnull
+    list: Cell<*const Link>,
+}
+
+impl Link {
+    const fn new() -> Self {
+        Self {
+            next: Cell::new(ptr::null()),
+            prev: Cell::new(ptr::null()),
+            list: Cell::new(ptr::null()),
+        }
+    }
+
+    fn is_linked(&self) -> bool {
+        !self.next.get().is_null() || !self.prev.get().is_null()
+    }
+
+    // 前後のリンクが自身を指し返していることを確かめる
+    fn debug_check(&self) {
+        let next = self.next.get();
+        let prev = self.prev.get();
+        debug_assert!(next.is_null() || ptr::eq(unsafe { (*next).prev.get() }, self), "next.prev != self");
+        debug_assert!(prev.is_null() || ptr::eq(unsafe { (*prev).next.get() }, self), "prev.next != self");
+    }
+
+    // 前後のリンクをつなぎ直して、自身を鎖から外す
+    fn unlink(&self) {
+        self.list.set(ptr::null());
+        if !self.is_linked() {
+            return;
+        }
+        self.debug_check();
+        let next = self.next.replace(ptr::null());
+        let prev = self.prev.replace(ptr::null());
+        unsafe {
+            if !next.is_null() {
+                (*next).prev.set(prev);
+            }
+            if !prev.is_null() {
+                (*prev).next.set(next);
+            }
+        }
+    }
+}
+
+// `Link`へのポインタを`Node`へのポインタに戻せるように、`link`を先頭に置く
+#[repr(C)]
 pub struct Node<T> {
+    link: Link,
     data: T,
-    next: Cell<*const Node<T>>,
-    prev: Cell<*const Node<T>>,
+    // 要素を`for_each_to_last`などのクロージャに渡している数
+    visits: Cell<usize>,
     _marker: PhantomPinned,
 }
 
 impl<T> Node<T> {
     pub const fn new(data: T) -> Self {
         Self {
+            link: Link::new(),
             data,
-            next: Cell::new(ptr::null()),
-            prev: Cell::new(ptr::null()),
+            visits: Cell::new(0),
             _marker: PhantomPinned,
         }
     }
 
-    pub fn insert_next<'a, 'b>(self: Pin<&'a Self>, node: Pin<&'b Self>) {
-        if &*self as *const _ == &*node {
+    // ノード全体から作ったポインタでないと、`Node`に戻したときに`data`に触れられない
+    fn as_link(&self) -> *const Link {
+        (self as *const Self).cast()
+    }
+
+    // `link`がnullか番兵なら`None`を返す
+    fn node_at(&self, link: *const Link) -> Option<&Self> {
+        if link.is_null() || link == self.link.list.get() {
+            None
+        } else {
+            Some(unsafe { &*link.cast::<Self>() })
+        }
+    }
+
+    fn next_node(&self) -> Option<&Self> {
+        self.node_at(self.link.next.get())
+    }
+
+    fn prev_node(&self) -> Option<&Self> {
+        self.node_at(self.link.prev.get())
+    }
+
+    fn assert_not_in_list(&self) {
+        assert!(self.link.list.get().is_null(), "nodes in a `List` must be linked through `List` methods");
+    }
+
+    /// `self`の次に`node`をつなぐ。`node`が他のノードとつながっていれば、先にそこから外す。
+    ///
+    /// # Panics
+    /// `self`か`node`が`List`に属している場合
+    pub fn insert_next(self: Pin<&Self>, node: Pin<&Self>) {
+        if ptr::eq(&*self, &*node) {
             return;
         }
-        let old_next = self.next.replace(&*node);
+        self.assert_not_in_list();
+        node.assert_not_in_list();
+        node.link.unlink();
+        let old_next = self.link.next.replace(node.as_link());
         if !old_next.is_null() {
             unsafe {
-                (*old_next).prev.set(&*node);
+                (*old_next).prev.set(node.as_link());
             }
         }
-        node.prev.set(&*self);
-        node.next.set(old_next);
+        node.link.prev.set(self.as_link());
+        node.link.next.set(old_next);
+        self.link.debug_check();
+        node.link.debug_check();
     }
 
-    pub fn insert_prev<'a, 'b>(self: Pin<&'a Self>, node: Pin<&'b Self>) {
-        if &*self as *const _ == &*node {
+    /// `self`の前に`node`をつなぐ。`node`が他のノードとつながっていれば、先にそこから外す。
+    ///
+    /// # Panics
+    /// `self`か`node`が`List`に属している場合
+    pub fn insert_prev(self: Pin<&Self>, node: Pin<&Self>) {
+        if ptr::eq(&*self, &*node) {
             return;
         }
-        let old_prev = self.prev.replace(&*node);
+        self.assert_not_in_list();
+        node.assert_not_in_list();
+        node.link.unlink();
+        let old_prev = self.link.prev.replace(node.as_link());
         if !old_prev.is_null() {
             unsafe {
-                (*old_prev).next.set(&*node);
+                (*old_prev).next.set(node.as_link());
+            }
+        }
+        node.link.next.set(self.as_link());
+        node.link.prev.set(old_prev);
+        self.link.debug_check();
+        node.link.debug_check();
+    }
+
+    // 要素を`f`に渡す。渡している間にノードがドロップされたら、参照が宙に浮く前に中断する
+    fn visit<U>(&self, f: impl FnOnce(&T) -> U) -> U {
+        struct Visit<'a>(&'a Cell<usize>);
+
+        impl Drop for Visit<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() - 1);
             }
         }
-        node.next.set(&*self);
-        node.prev.set(old_prev);
+
+        self.visits.set(self.visits.get() + 1);
+        let _visit = Visit(&self.visits);
+        f(&self.data)
     }
 
+    /// `self`から鎖の末尾まで、順に要素を`f`に渡す。
+    ///
+    /// `f`の中で他のノードをつなぎ替えたりドロップしたりしてもよく、次のノードは`f`を呼んだ後に読む。
+    /// ただし要素を渡しているノード自身をドロップすると、プロセスを中断する。
     pub fn for_each_to_last(self: Pin<&Self>, mut f: impl FnMut(&T)) {
         let mut node = &*self;
-        while {
-            f(&node.data);
-            !node.next.get().is_null()
-        } {
-            node = unsafe { &*node.next.get() };
+        loop {
+            node.visit(&mut f);
+            match node.next_node() {
+                Some(next) => node = next,
+                None => break,
+            }
         }
     }
 
+    /// `self`から鎖の先頭まで、順に要素を`f`に渡す。条件は`for_each_to_last`と同じ。
     pub fn for_each_to_first(self: Pin<&Self>, mut f: impl FnMut(&T)) {
         let mut node = &*self;
-        while {
-            f(&node.data);
-            !node.prev.get().is_null()
-        } {
-            node = unsafe { &*node.prev.get() };
+        loop {
+            node.visit(&mut f);
+            match node.prev_node() {
+                Some(prev) => node = prev,
+                None => break,
+            }
         }
     }
 
+    /// 次のノードの要素に`f`を適用する。`f`の中で次のノードをドロップすると、プロセスを中断する。
     pub fn map_next<U>(self: Pin<&Self>, f: impl FnOnce(&T) -> U) -> Option<U> {
-        let next = self.next.get();
-        if next.is_null() {
-            None
-        } else {
-            Some(unsafe { f(&(*next).data) })
-        }
+        self.get_ref().next_node().map(|node| node.visit(f))
     }
 
+    /// 前のノードの要素に`f`を適用する。`f`の中で前のノードをドロップすると、プロセスを中断する。
     pub fn map_prev<U>(self: Pin<&Self>, f: impl FnOnce(&T) -> U) -> Option<U> {
-        let prev = self.prev.get();
-        if prev.is_null() {
-            None
-        } else {
-            Some(unsafe { f(&(*prev).data) })
-        }
-    }
-
-    fn cut_by_ref(&self) {
-        let next = self.next.replace(ptr::null());
-        let prev = self.prev.replace(ptr::null());
-        unsafe {
-            if !next.is_null() {
-                (*next).prev.set(prev);
-            }
-            if !prev.is_null() {
-                (*prev).next.set(next);
-            }
-        }
+        self.get_ref().prev_node().map(|node| node.visit(f))
     }
 
+    /// 前後のノードから切り離す。
+    ///
+    /// # Panics
+    /// `self`が`List`に属している場合。リストからは`List::remove`で取り除く。
     pub fn cut(self: Pin<&Self>) {
-        self.cut_by_ref();
+        self.assert_not_in_list();
+        self.link.unlink();
     }
 }
 
@@ -111,23 +243,28 @@ impl<T> Deref for Node<T> {
 
 impl<T> Drop for Node<T> {
     fn drop(&mut self) {
-        let next = self.next.get();
-        let prev = self.prev.get();
-        unsafe {
-            if !next.is_null() {
-                (*next).prev.set(prev);
-            }
-            if !prev.is_null() {
-                (*prev).next.set(next);
-            }
+        if self.visits.get() != 0 {
+            // パニックしても巻き戻しの途中で領域が解放されるので、ここで止めるしかない
+            eprintln!("a `Node` was dropped while its data was borrowed by `for_each_to_last` or the like");
+            process::abort();
         }
+        self.link.unlink();
     }
 }
+
+// Miriでも実行できるように、ループは小さく保つ
+// (`cargo +nightly miri test`、Tree Borrowsは`MIRIFLAGS=-Zmiri-tree-borrows`)
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::pin::pin;
 
+    fn collect<T: Copy>(node: Pin<&Node<T>>) -> Vec<T> {
+        let mut v = Vec::new();
+        node.for_each_to_last(|x| v.push(*x));
+        v
+    }
+
     #[test]
     fn pin_test() {
         let a = pin!(Node::new(10));
@@ -139,4 +276,138 @@ mod tests {
         let a_ref = a.as_ref();
         assert!(a_ref.map_prev(|x| *x).is_none());
     }
+
+    #[test]
+    fn relink_test() {
+        let a = pin!(Node::new(1));
+        let b = pin!(Node::new(2));
+        let c = pin!(Node::new(3));
+        let d = pin!(Node::new(4));
+        a.as_ref().insert_next(c.as_ref());
+        a.as_ref().insert_next(b.as_ref());
+        assert_eq!(vec![1, 2, 3], collect(a.as_ref()));
+
+        // 別の鎖に入れると元の鎖からは外れる
+        d.as_ref().insert_next(b.as_ref());
+        assert_eq!(vec![1, 3], collect(a.as_ref()));
+        assert_eq!(vec![4, 2], collect(d.as_ref()));
+
+        // 同じ鎖の中での入れ替え
+        a.as_ref().insert_prev(c.as_ref());
+        assert_eq!(vec![3, 1], collect(c.as_ref()));
+        c.as_ref().insert_next(a.as_ref());
+        assert_eq!(vec![3, 1], collect(c.as_ref()));
+        a.as_ref().insert_next(a.as_ref());
+        assert_eq!(vec![3, 1], collect(c.as_ref()));
+    }
+
+    #[test]
+    fn drop_order_test() {
+        for order in [[0, 1, 2, 3], [3, 2, 1, 0], [1, 2, 0, 3], [2, 0, 3, 1]] {
+            let mut nodes = (0..4).map(|i| Some(Box::pin(Node::new(i)))).collect::<Vec<_>>();
+            let first = pin!(Node::new(4));
+            for node in nodes.iter().rev().flatten() {
+                first.as_ref().insert_next(node.as_ref());
+            }
+            let mut alive = vec![4, 0, 1, 2, 3];
+            for i in order {
+                nodes[i] = None;
+                alive.retain(|&x| x != i);
+                assert_eq!(alive, collect(first.as_ref()));
+                for node in nodes.iter().flatten() {
+                    node.link.debug_check();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn drop_first_test() {
+        let b = Box::pin(Node::new(2));
+        {
+            let a = pin!(Node::new(1));
+            a.as_ref().insert_next(b.as_ref());
+            let c = pin!(Node::new(3));
+            b.as_ref().insert_next(c.as_ref());
+            // `c`、`a`の順にドロップされる
+        }
+        assert!(b.as_ref().map_prev(|x| *x).is_none());
+        assert!(b.as_ref().map_next(|x| *x).is_none());
+        drop(b);
+    }
+
+    #[test]
+    fn for_each_drop_test() {
+        let a = pin!(Node::new(1));
+        let mut nodes = (2..=4).map(|i| Some(Box::pin(Node::new(i)))).collect::<Vec<_>>();
+        for node in nodes.iter().rev().flatten() {
+            a.as_ref().insert_next(node.as_ref());
+        }
+        let extra = pin!(Node::new(10));
+        let mut v = Vec::new();
+        // 渡されているノードの先を`f`の中でドロップしたりつないだりする
+        a.as_ref().for_each_to_last(|&x| {
+            v.push(x);
+            match x {
+                1 => nodes[0] = None,
+                3 => nodes[2].as_ref().unwrap().as_ref().insert_prev(extra.as_ref()),
+                _ => {}
+            }
+        });
+        assert_eq!(vec![1, 3, 10, 4], v);
+        assert_eq!(Some(3), a.as_ref().map_next(|x| {
+            nodes[2] = None;
+            *x
+        }));
+        assert_eq!(vec![1, 3, 10], collect(a.as_ref()));
+    }
+
+    #[test]
+    fn cut_test() {
+        let a = pin!(Node::new(1));
+        let b = pin!(Node::new(2));
+        let c = pin!(Node::new(3));
+        a.as_ref().insert_next(b.as_ref());
+        b.as_ref().insert_next(c.as_ref());
+        b.as_ref().cut();
+        b.as_ref().cut();
+        assert_eq!(vec![1, 3], collect(a.as_ref()));
+        assert_eq!(vec![2], collect(b.as_ref()));
+        a.as_ref().cut();
+        assert_eq!(vec![3], collect(c.as_ref()));
+    }
+
+    #[test]
+    fn list_drop_order_test() {
+        let a = Box::pin(Node::new(1));
+        let b = Box::pin(Node::new(2));
+        let bare = pin!(Node::new(0));
+        {
+            let mut list = pin!(List::new());
+            // 鎖につながっているノードは外してからリストに入れる
+            bare.as_ref().insert_next(a.as_ref());
+            list.as_mut().push_back(a.as_ref());
+            list.as_mut().push_back(b.as_ref());
+            assert!(bare.as_ref().map_next(|x| *x).is_none());
+            assert_eq!(vec![1, 2], list.iter().copied().collect::<Vec<_>>());
+            list.as_mut().pop_front();
+        }
+        // 取り出されたノードとリストに残っていたノードは、どちらもつながりがない
+        a.as_ref().link.debug_check();
+        assert!(!a.link.is_linked() && !b.link.is_linked());
+        drop(b);
+        bare.as_ref().insert_next(a.as_ref());
+        drop(a);
+        assert!(bare.as_ref().map_next(|x| *x).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_listed_node_test() {
+        let a = pin!(Node::new(1));
+        let mut list = pin!(List::new());
+        let mut other = pin!(List::new());
+        list.as_mut().push_back(a.as_ref());
+        other.as_mut().push_back(a.as_ref());
+    }
 }
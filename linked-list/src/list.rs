@@ -0,0 +1,412 @@
+use std::{
+    cell::Cell,
+    iter::Rev,
+    marker::{PhantomData, PhantomPinned},
+    pin::Pin,
+    ptr,
+};
+
+use crate::{Link, Node};
+
+mod cursor;
+
+pub use cursor::{Cursor, CursorMut};
+
+/// 番兵を持つ双方向リストの先頭
+///
+/// ノードは`'a`の間借用されるので、リストより先にドロップされることはない。
+/// リストがドロップされると、残っているノードはすべて切り離される。
+/// 番兵は自身を指すので、リストも`Pin`で固定してから使う。
+///
+/// ノードはリストより先に宣言する必要がある。
+/// ```compile_fail
+/// use linked_list::{List, Node};
+/// use std::pin::pin;
+///
+/// let mut list = pin!(List::new());
+/// let node = pin!(Node::new(1));
+/// list.as_mut().push_back(node.as_ref());
+/// ```
+///
+/// リストに属するノードのつなぎ替えは`&mut`を取るメソッドでしかできないので、
+/// イテレータが生きている間にノードが外されたりドロップされたりすることはない。
+/// ```compile_fail
+/// use linked_list::{List, Node};
+/// use std::pin::pin;
+///
+/// let node = pin!(Node::new(1));
+/// let mut list = pin!(List::new());
+/// list.as_mut().push_back(node.as_ref());
+/// for _ in list.iter_forward() {
+///     list.as_mut().pop_front();
+/// }
+/// ```
+pub struct List<'a, T> {
+    // 先頭のノードを`next`、末尾のノードを`prev`で指す。空なら自身を指し、まだ使われていなければnull
+    sentinel: Link,
+    len: usize,
+    // 短い寿命のノードを入れられないように`'a`について不変にする
+    _marker: PhantomData<Cell<Pin<&'a Node<T>>>>,
+    _pinned: PhantomPinned,
+}
+
+impl<T> Default for List<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> List<'a, T> {
+    pub const fn new() -> Self {
+        Self {
+            sentinel: Link::new(),
+            len: 0,
+            _marker: PhantomData,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    // 初めて使うときに番兵を自身につなぐ
+    fn sentinel(self: Pin<&Self>) -> *const Link {
+        let sentinel = &self.get_ref().sentinel;
+        let ptr = sentinel as *const Link;
+        if sentinel.next.get().is_null() {
+            sentinel.next.set(ptr);
+            sentinel.prev.set(ptr);
+        }
+        ptr
+    }
+
+    // 要素数は番兵と関係ないので、`Pin`を外して書き換えてよい
+    fn len_mut(self: Pin<&mut Self>) -> &mut usize {
+        unsafe { &mut self.get_unchecked_mut().len }
+    }
+
+    // `link`がnullか番兵なら`None`を返す
+    fn node_at(&self, link: *const Link) -> Option<Pin<&'a Node<T>>> {
+        if link.is_null() || ptr::eq(link, &self.sentinel) {
+            None
+        } else {
+            // リストに入っているノードは`'a`の間生きている
+            Some(unsafe { Pin::new_unchecked(&*link.cast::<Node<T>>()) })
+        }
+    }
+
+    // `prev`と`next`の間に`node`をつなぐ。リストに属さない鎖につながっていれば、先にそこから外す。
+    fn link_between(self: Pin<&mut Self>, node: Pin<&'a Node<T>>, prev: *const Link, next: *const Link) {
+        assert!(node.link.list.get().is_null(), "the node is already in a list");
+        node.link.unlink();
+        let link = node.as_link();
+        node.link.prev.set(prev);
+        node.link.next.set(next);
+        node.link.list.set(self.as_ref().sentinel());
+        unsafe {
+            (*prev).next.set(link);
+            (*next).prev.set(link);
+        }
+        node.link.debug_check();
+        *self.len_mut() += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn front_link(&self) -> Option<Pin<&'a Node<T>>> {
+        self.node_at(self.sentinel.next.get())
+    }
+
+    fn back_link(&self) -> Option<Pin<&'a Node<T>>> {
+        self.node_at(self.sentinel.prev.get())
+    }
+
+    pub fn front(&self) -> Option<Pin<&'a Node<T>>> {
+        self.front_link()
+    }
+
+    pub fn back(&self) -> Option<Pin<&'a Node<T>>> {
+        self.back_link()
+    }
+
+    fn contains(&self, node: &Node<T>) -> bool {
+        ptr::eq(node.link.list.get(), &self.sentinel)
+    }
+
+    /// `node`が他のノードとつながっていれば、先にそこから外す。
+    ///
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn push_front(self: Pin<&mut Self>, node: Pin<&'a Node<T>>) {
+        let sentinel = self.as_ref().sentinel();
+        let first = self.sentinel.next.get();
+        self.link_between(node, sentinel, first);
+    }
+
+    /// `node`が他のノードとつながっていれば、先にそこから外す。
+    ///
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn push_back(self: Pin<&mut Self>, node: Pin<&'a Node<T>>) {
+        let sentinel = self.as_ref().sentinel();
+        let last = self.sentinel.prev.get();
+        self.link_between(node, last, sentinel);
+    }
+
+    /// 先頭のノードをリストから切り離して返す。
+    pub fn pop_front(self: Pin<&mut Self>) -> Option<Pin<&'a Node<T>>> {
+        let node = self.front_link()?;
+        node.link.unlink();
+        *self.len_mut() -= 1;
+        Some(node)
+    }
+
+    /// 末尾のノードをリストから切り離して返す。
+    pub fn pop_back(self: Pin<&mut Self>) -> Option<Pin<&'a Node<T>>> {
+        let node = self.back_link()?;
+        node.link.unlink();
+        *self.len_mut() -= 1;
+        Some(node)
+    }
+
+    /// `node`をリストから取り除く。
+    ///
+    /// # Panics
+    /// `node`がこのリストに属していない場合
+    pub fn remove(self: Pin<&mut Self>, node: Pin<&'a Node<T>>) {
+        assert!(self.contains(&node), "the node is not in this list");
+        node.link.unlink();
+        *self.len_mut() -= 1;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_forward()
+    }
+
+    /// 先頭から末尾へ順に返すイテレータ
+    pub fn iter_forward(&self) -> Iter<'_, T> {
+        Iter {
+            head: self.sentinel.next.get(),
+            tail: self.sentinel.prev.get(),
+            sentinel: &self.sentinel,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 末尾から先頭へ順に返すイテレータ
+    pub fn iter_backward(&self) -> Rev<Iter<'_, T>> {
+        self.iter_forward().rev()
+    }
+
+    /// `node`から末尾へ順に返すイテレータ。逆順にたどると末尾から`node`までを返す。
+    ///
+    /// # Panics
+    /// `node`がこのリストに属していない場合
+    pub fn iter_from(&self, node: Pin<&Node<T>>) -> Iter<'_, T> {
+        assert!(self.contains(&node), "the node is not in this list");
+        Iter {
+            head: node.as_link(),
+            tail: self.sentinel.prev.get(),
+            sentinel: &self.sentinel,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for List<'_, T> {
+    fn drop(&mut self) {
+        let sentinel = &self.sentinel as *const Link;
+        let mut link = self.sentinel.next.get();
+        while !link.is_null() && link != sentinel {
+            unsafe {
+                let next = (*link).next.get();
+                (*link).next.set(ptr::null());
+                (*link).prev.set(ptr::null());
+                (*link).list.set(ptr::null());
+                link = next;
+            }
+        }
+    }
+}
+
+/// `List`の要素を順に返すイテレータ
+///
+/// リストを借用している間はノードのつなぎ替えができないので、両端から読み進めるだけでよい。
+pub struct Iter<'a, T> {
+    head: *const Link,
+    tail: *const Link,
+    sentinel: *const Link,
+    _marker: PhantomData<&'a Node<T>>,
+}
+
+impl<T> Iter<'_, T> {
+    fn finish(&mut self) {
+        self.head = ptr::null();
+        self.tail = ptr::null();
+    }
+
+    fn is_end(&self, link: *const Link) -> bool {
+        link.is_null() || link == self.sentinel
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_end(self.head) {
+            return None;
+        }
+        let node = unsafe { &*self.head.cast::<Node<T>>() };
+        if self.head == self.tail {
+            self.finish();
+        } else {
+            self.head = node.link.next.get();
+        }
+        Some(&node.data)
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.is_end(self.tail) {
+            return None;
+        }
+        let node = unsafe { &*self.tail.cast::<Node<T>>() };
+        if self.head == self.tail {
+            self.finish();
+        } else {
+            self.tail = node.link.prev.get();
+        }
+        Some(&node.data)
+    }
+}
+
+impl<'b, T> IntoIterator for &'b List<'_, T> {
+    type Item = &'b T;
+    type IntoIter = Iter<'b, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+
+    #[test]
+    fn list_test() {
+        let a = pin!(Node::new(1));
+        let b = pin!(Node::new(2));
+        let c = pin!(Node::new(3));
+        let mut list = pin!(List::new());
+        assert!(list.is_empty());
+        assert!(list.as_mut().pop_front().is_none());
+        assert_eq!(0, list.len());
+
+        list.as_mut().push_back(b.as_ref());
+        list.as_mut().push_front(a.as_ref());
+        list.as_mut().push_back(c.as_ref());
+        assert_eq!(3, list.len());
+        assert_eq!(vec![1, 2, 3], list.iter().copied().collect::<Vec<_>>());
+
+        // 端のノードでは番兵をたどらない
+        assert_eq!(None, a.as_ref().map_prev(|x| *x));
+        assert_eq!(Some(2), a.as_ref().map_next(|x| *x));
+        let mut v = Vec::new();
+        b.as_ref().for_each_to_last(|x| v.push(*x));
+        assert_eq!(vec![2, 3], v);
+
+        list.as_mut().remove(b.as_ref());
+        assert_eq!(2, list.len());
+        assert_eq!(vec![1, 3], list.iter().copied().collect::<Vec<_>>());
+        assert_eq!(Some(3), list.as_mut().pop_back().map(|n| **n));
+        assert_eq!(Some(1), list.front().map(|n| **n));
+        assert_eq!(Some(1), list.as_mut().pop_front().map(|n| **n));
+        assert!(list.is_empty());
+        assert_eq!(0, list.len());
+
+        list.as_mut().push_back(c.as_ref());
+        list.as_mut().push_back(a.as_ref());
+        assert_eq!(Some(3), list.front().map(|n| **n));
+        assert_eq!(Some(1), list.back().map(|n| **n));
+    }
+
+    #[test]
+    fn iter_test() {
+        let nodes = [pin!(Node::new(1)), pin!(Node::new(2)), pin!(Node::new(3)), pin!(Node::new(4))];
+        let mut list = pin!(List::new());
+        assert_eq!(None, list.iter_forward().next());
+        assert_eq!(None, list.iter_backward().next());
+        for node in &nodes {
+            list.as_mut().push_back(node.as_ref());
+        }
+        assert_eq!(vec![1, 2, 3, 4], list.iter_forward().copied().collect::<Vec<_>>());
+        assert_eq!(vec![4, 3, 2, 1], list.iter_backward().copied().collect::<Vec<_>>());
+        assert_eq!(vec![2, 3, 4], list.iter_from(nodes[1].as_ref()).copied().collect::<Vec<_>>());
+        assert_eq!(vec![4, 3], list.iter_from(nodes[2].as_ref()).rev().copied().collect::<Vec<_>>());
+
+        let mut iter = list.iter();
+        assert_eq!(Some(&1), iter.next());
+        assert_eq!(Some(&4), iter.next_back());
+        assert_eq!(Some(&2), iter.next());
+        assert_eq!(Some(&3), iter.next_back());
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next_back());
+
+        // ノード自体はリストの借用と関係なく`'a`の間使える
+        let first = list.front().unwrap();
+        list.as_mut().pop_front();
+        assert_eq!(1, **first);
+    }
+
+    #[test]
+    #[should_panic]
+    fn iter_from_other_list_test() {
+        let a = pin!(Node::new(1));
+        let mut list = pin!(List::new());
+        let other = pin!(List::<i32>::new());
+        list.as_mut().push_back(a.as_ref());
+        other.iter_from(a.as_ref());
+    }
+
+    #[test]
+    fn list_drop_test() {
+        let a = pin!(Node::new(1));
+        let b = pin!(Node::new(2));
+        {
+            let mut list = pin!(List::new());
+            list.as_mut().push_back(a.as_ref());
+            list.as_mut().push_back(b.as_ref());
+        }
+        // リストがドロップされたので、ノードは互いに切り離されている
+        assert!(a.as_ref().map_next(|x| *x).is_none());
+        assert!(b.as_ref().map_prev(|x| *x).is_none());
+        a.as_ref().insert_next(b.as_ref());
+        assert_eq!(Some(2), a.as_ref().map_next(|x| *x));
+    }
+
+    #[test]
+    #[should_panic]
+    fn link_into_list_test() {
+        let a = pin!(Node::new(1));
+        let mut list = pin!(List::new());
+        list.as_mut().push_back(a.as_ref());
+        let b = pin!(Node::new(2));
+        a.as_ref().insert_next(b.as_ref());
+    }
+
+    #[test]
+    #[should_panic]
+    fn cut_in_list_test() {
+        let a = pin!(Node::new(1));
+        let mut list = pin!(List::new());
+        list.as_mut().push_back(a.as_ref());
+        a.as_ref().cut();
+    }
+}
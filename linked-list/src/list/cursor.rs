@@ -0,0 +1,302 @@
+use std::{mem, pin::Pin};
+
+use crate::{Link, List, Node};
+
+impl<'a, T> List<'a, T> {
+    // 番兵がまだ使われていなければ、次も前も番兵とみなす
+    fn step(&self, link: *const Link, forward: bool) -> *const Link {
+        let link = unsafe { &*link };
+        let next = if forward { link.next.get() } else { link.prev.get() };
+        if next.is_null() {
+            &self.sentinel
+        } else {
+            next
+        }
+    }
+
+    fn node_ref(&self, link: *const Link) -> Option<&'a T> {
+        self.node_at(link).map(Pin::get_ref).map(|node| &node.data)
+    }
+
+    fn iter_forward_nodes(&self) -> impl Iterator<Item = Pin<&'a Node<T>>> + '_ {
+        let mut link = self.step(&self.sentinel, true);
+        std::iter::from_fn(move || {
+            let node = self.node_at(link)?;
+            link = self.step(link, true);
+            Some(node)
+        })
+    }
+
+    /// 先頭の要素を指すカーソル。空なら番兵を指す。
+    pub fn cursor_front(&self) -> Cursor<'_, 'a, T> {
+        Cursor {
+            current: self.step(&self.sentinel, true),
+            list: self,
+        }
+    }
+
+    /// 末尾の要素を指すカーソル。空なら番兵を指す。
+    pub fn cursor_back(&self) -> Cursor<'_, 'a, T> {
+        Cursor {
+            current: self.step(&self.sentinel, false),
+            list: self,
+        }
+    }
+
+    pub fn cursor_front_mut(self: Pin<&mut Self>) -> CursorMut<'_, 'a, T> {
+        let current = self.as_ref().sentinel();
+        let mut cursor = CursorMut { list: self, current };
+        cursor.move_next();
+        cursor
+    }
+
+    pub fn cursor_back_mut(self: Pin<&mut Self>) -> CursorMut<'_, 'a, T> {
+        let current = self.as_ref().sentinel();
+        let mut cursor = CursorMut { list: self, current };
+        cursor.move_prev();
+        cursor
+    }
+}
+
+/// `List`の要素を指すカーソル
+///
+/// `std::collections::linked_list::Cursor`と同様に、末尾と先頭の間に要素を持たない位置 (番兵) がある。
+/// カーソルは`List`にしか作れない。リストに属さない鎖は`Node::for_each_to_last`などでたどる。
+pub struct Cursor<'b, 'a, T> {
+    list: &'b List<'a, T>,
+    current: *const Link,
+}
+
+impl<T> Clone for Cursor<'_, '_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Cursor<'_, '_, T> {}
+
+impl<'b, T> Cursor<'b, '_, T> {
+    /// 番兵を指していれば`None`を返す。
+    pub fn current(&self) -> Option<&'b T> {
+        self.list.node_ref(self.current)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.list.step(self.current, true);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.list.step(self.current, false);
+    }
+
+    pub fn peek_next(&self) -> Option<&'b T> {
+        self.list.node_ref(self.list.step(self.current, true))
+    }
+
+    pub fn peek_prev(&self) -> Option<&'b T> {
+        self.list.node_ref(self.list.step(self.current, false))
+    }
+}
+
+/// ノードの挿入・削除ができる`List`のカーソル
+///
+/// リストに属さない鎖をまとめて移すことはできない。
+/// 鎖は各ノードの寿命を覚えていないので、渡したノードより先にドロップされるノードが混ざりうるからである。
+/// 鎖のノードは`insert_after`などで1つずつ移す (移したノードは鎖から外れる)。
+pub struct CursorMut<'b, 'a, T> {
+    list: Pin<&'b mut List<'a, T>>,
+    current: *const Link,
+}
+
+impl<'a, T> CursorMut<'_, 'a, T> {
+    pub fn as_cursor(&self) -> Cursor<'_, 'a, T> {
+        Cursor {
+            list: &self.list,
+            current: self.current,
+        }
+    }
+
+    /// 番兵を指していれば`None`を返す。
+    pub fn current(&self) -> Option<&T> {
+        self.list.node_ref(self.current)
+    }
+
+    /// 指しているノード。ノードはカーソルと関係なく`'a`の間使える。
+    pub fn current_node(&self) -> Option<Pin<&'a Node<T>>> {
+        self.list.node_at(self.current)
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.list.step(self.current, true);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.list.step(self.current, false);
+    }
+
+    pub fn peek_next(&self) -> Option<&T> {
+        self.list.node_ref(self.list.step(self.current, true))
+    }
+
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.list.node_ref(self.list.step(self.current, false))
+    }
+
+    /// 現在の位置の後ろに`node`を挿入する。番兵を指しているときは先頭に挿入する。
+    ///
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn insert_after(&mut self, node: Pin<&'a Node<T>>) {
+        let next = self.list.step(self.current, true);
+        self.list.as_mut().link_between(node, self.current, next);
+    }
+
+    /// 現在の位置の前に`node`を挿入する。番兵を指しているときは末尾に挿入する。
+    ///
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn insert_before(&mut self, node: Pin<&'a Node<T>>) {
+        let prev = self.list.step(self.current, false);
+        self.list.as_mut().link_between(node, prev, self.current);
+    }
+
+    /// 指しているノードをリストから取り除いて返し、カーソルを次の位置に進める。
+    pub fn remove_current(&mut self) -> Option<Pin<&'a Node<T>>> {
+        let node = self.current_node()?;
+        self.move_next();
+        node.link.unlink();
+        *self.list.as_mut().len_mut() -= 1;
+        Some(node)
+    }
+
+    // `other`のノードをすべて`prev`と`next`の間に移す
+    fn splice_between(&mut self, mut other: Pin<&mut List<'a, T>>, prev: *const Link, next: *const Link) {
+        let (Some(first), Some(last)) = (other.front_link(), other.back_link()) else {
+            return;
+        };
+        let sentinel = self.list.as_ref().sentinel();
+        for node in other.iter_forward_nodes() {
+            node.link.list.set(sentinel);
+        }
+        let other_sentinel = other.as_ref().sentinel();
+        unsafe {
+            (*other_sentinel).next.set(other_sentinel);
+            (*other_sentinel).prev.set(other_sentinel);
+            first.link.prev.set(prev);
+            last.link.next.set(next);
+            (*prev).next.set(first.as_link());
+            (*next).prev.set(last.as_link());
+        }
+        first.link.debug_check();
+        last.link.debug_check();
+        *self.list.as_mut().len_mut() += mem::take(other.as_mut().len_mut());
+    }
+
+    /// `other`のノードをすべて現在の位置の後ろに移す。`other`は空になる。
+    ///
+    /// 移せるのは`List`だけで、リストに属さない鎖は受け取らない。
+    pub fn splice_after(&mut self, other: Pin<&mut List<'a, T>>) {
+        let next = self.list.step(self.current, true);
+        self.splice_between(other, self.current, next);
+    }
+
+    /// `other`のノードをすべて現在の位置の前に移す。`other`は空になる。
+    ///
+    /// 移せるのは`List`だけで、リストに属さない鎖は受け取らない。
+    pub fn splice_before(&mut self, other: Pin<&mut List<'a, T>>) {
+        let prev = self.list.step(self.current, false);
+        self.splice_between(other, prev, self.current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+
+    fn collect<T: Copy>(list: &List<'_, T>) -> Vec<T> {
+        let v = list.iter().copied().collect::<Vec<_>>();
+        assert_eq!(v.len(), list.len());
+        v
+    }
+
+    #[test]
+    fn cursor_test() {
+        let nodes = [pin!(Node::new(1)), pin!(Node::new(2)), pin!(Node::new(3))];
+        let mut list = pin!(List::new());
+        let cursor = list.cursor_front();
+        assert_eq!(None, cursor.current());
+        assert_eq!(None, cursor.peek_next());
+
+        for node in &nodes {
+            list.as_mut().push_back(node.as_ref());
+        }
+        let mut cursor = list.cursor_front();
+        assert_eq!(Some(&1), cursor.current());
+        assert_eq!(None, cursor.peek_prev());
+        assert_eq!(Some(&2), cursor.peek_next());
+        cursor.move_prev();
+        assert_eq!(None, cursor.current());
+        cursor.move_prev();
+        assert_eq!(Some(&3), cursor.current());
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(Some(&1), cursor.current());
+        assert_eq!(Some(&3), list.cursor_back().current());
+    }
+
+    #[test]
+    fn cursor_mut_test() {
+        let nodes = [pin!(Node::new(1)), pin!(Node::new(2)), pin!(Node::new(3)), pin!(Node::new(4))];
+        let others = [pin!(Node::new(10)), pin!(Node::new(20))];
+        let mut list = pin!(List::new());
+        let mut other = pin!(List::new());
+
+        let mut cursor = list.as_mut().cursor_front_mut();
+        assert_eq!(None, cursor.current());
+        // 番兵の後ろは先頭、前は末尾
+        cursor.insert_after(nodes[1].as_ref());
+        cursor.insert_before(nodes[3].as_ref());
+        cursor.insert_after(nodes[0].as_ref());
+        cursor.move_next();
+        cursor.move_next();
+        cursor.insert_after(nodes[2].as_ref());
+        assert_eq!(Some(&2), cursor.current());
+        assert_eq!(Some(&3), cursor.peek_next());
+        assert_eq!(Some(&1), cursor.as_cursor().peek_prev());
+        assert_eq!(vec![1, 2, 3, 4], collect(&list));
+
+        let mut cursor = list.as_mut().cursor_back_mut();
+        assert_eq!(Some(4), cursor.remove_current().map(|n| **n));
+        assert_eq!(None, cursor.current());
+        cursor.move_next();
+        assert_eq!(Some(1), cursor.remove_current().map(|n| **n));
+        assert_eq!(Some(&2), cursor.current());
+        assert_eq!(vec![2, 3], collect(&list));
+
+        for node in &others {
+            other.as_mut().push_back(node.as_ref());
+        }
+        let mut cursor = list.as_mut().cursor_front_mut();
+        cursor.splice_after(other.as_mut());
+        assert!(other.is_empty());
+        assert_eq!(0, other.len());
+        assert_eq!(vec![2, 10, 20, 3], collect(&list));
+        // 移したノードはこのリストのものとして扱われる
+        assert_eq!(vec![20, 3], list.iter_from(others[1].as_ref()).copied().collect::<Vec<_>>());
+
+        let mut cursor = list.as_mut().cursor_front_mut();
+        cursor.remove_current();
+        cursor.remove_current();
+        cursor.remove_current();
+        other.as_mut().push_back(nodes[0].as_ref());
+        other.as_mut().push_back(nodes[1].as_ref());
+        let mut cursor = list.as_mut().cursor_front_mut();
+        cursor.splice_before(other.as_mut());
+        assert_eq!(vec![1, 2, 3], collect(&list));
+        let mut cursor = list.as_mut().cursor_back_mut();
+        cursor.splice_before(other.as_mut());
+        assert_eq!(vec![1, 2, 3], collect(&list));
+        assert_eq!(vec![3, 2, 1], list.iter_backward().copied().collect::<Vec<_>>());
+    }
+}
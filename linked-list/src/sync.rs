@@ -0,0 +1,604 @@
+use std::{
+    hint,
+    marker::{PhantomData, PhantomPinned},
+    ops::Deref,
+    pin::Pin,
+    ptr::{self, NonNull},
+    process,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+    thread,
+};
+
+use atomic_nonnull::AtomicNonNull;
+
+// 今のスレッドの識別子。スレッドローカル変数の番地は、生きているスレッドの間で重ならない
+fn current_thread_id() -> usize {
+    thread_local!(static ID: u8 = const { 0 });
+    ID.with(|id| id as *const u8 as usize)
+}
+
+// リストごとのロック。保持しているスレッドの識別子を持ち、誰も保持していなければ0
+// しばらく回っても取れなければ、保持しているスレッドに実行を譲る
+struct SpinLock(AtomicUsize);
+
+impl SpinLock {
+    const fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    // 同じスレッドが保持しているときは、待ち続けずにパニックする
+    fn lock(&self) {
+        let id = current_thread_id();
+        while let Err(owner) = self.0.compare_exchange_weak(0, id, Ordering::Acquire, Ordering::Relaxed) {
+            assert_ne!(owner, id, "the lock is already held by the current thread");
+            let mut spins = 0;
+            while self.0.load(Ordering::Relaxed) != 0 {
+                if spins < 64 {
+                    spins += 1;
+                    hint::spin_loop();
+                } else {
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+
+    fn unlock(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+
+    // 保持しているスレッドしか自身の識別子を書き込まないので、`Relaxed`で読んでも判定は正しい
+    fn is_held_by_current_thread(&self) -> bool {
+        self.0.load(Ordering::Relaxed) == current_thread_id()
+    }
+}
+
+// 前後のリンク。属しているリストのロックを取っている間だけ読み書きするので、順序は`Relaxed`でよい
+struct SyncLink {
+    next: AtomicNonNull<SyncLink>,
+    prev: AtomicNonNull<SyncLink>,
+}
+
+impl SyncLink {
+    const fn new() -> Self {
+        Self {
+            next: AtomicNonNull::new(NonNull::dangling()),
+            prev: AtomicNonNull::new(NonNull::dangling()),
+        }
+    }
+
+    fn next(&self) -> NonNull<SyncLink> {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    fn prev(&self) -> NonNull<SyncLink> {
+        self.prev.load(Ordering::Relaxed)
+    }
+
+    // ロックを取った状態で、`self`を前後のリンクから外す
+    unsafe fn unlink(&self) {
+        let (next, prev) = (self.next(), self.prev());
+        next.as_ref().prev.store(prev, Ordering::Relaxed);
+        prev.as_ref().next.store(next, Ordering::Relaxed);
+    }
+
+    // ロックを取った状態で、`self`を`prev`と`next`の間につなぐ
+    unsafe fn link_between(&self, this: NonNull<SyncLink>, prev: NonNull<SyncLink>, next: NonNull<SyncLink>) {
+        self.prev.store(prev, Ordering::Relaxed);
+        self.next.store(next, Ordering::Relaxed);
+        prev.as_ref().next.store(this, Ordering::Relaxed);
+        next.as_ref().prev.store(this, Ordering::Relaxed);
+    }
+}
+
+/// 複数のスレッドから`SyncList`につなげるノード
+///
+/// `'l`はつなぐリストの借用期間で、つないだノードがドロップされるまでリストは生きている。
+/// ドロップ時にはリストのロックを取って自身を外す。
+/// 同じスレッドがそのリストの`SyncListGuard`を持っていれば、ロックを取り直さずにそのまま外す。
+/// ただしそのガードが`iter`などで要素を貸した後だと、貸した参照が宙に浮きうるのでプロセスを中断する。
+#[repr(C)]
+pub struct SyncNode<'l, T> {
+    link: SyncLink,
+    // 属しているリスト。nullでないときは、そのリストのロックを取っているときだけ書き換える
+    list: AtomicPtr<SyncList<T>>,
+    data: T,
+    // リストより長く生きるノードを入れられないように`'l`について不変にする
+    _marker: PhantomData<fn(&'l ()) -> &'l ()>,
+    _pinned: PhantomPinned,
+}
+
+unsafe impl<T: Send> Send for SyncNode<'_, T> {}
+unsafe impl<T: Sync> Sync for SyncNode<'_, T> {}
+
+impl<T> SyncNode<'_, T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            link: SyncLink::new(),
+            list: AtomicPtr::new(ptr::null_mut()),
+            data,
+            _marker: PhantomData,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    // ノード全体から作ったポインタでないと、`SyncNode`に戻したときに`data`に触れられない
+    fn as_link(&self) -> NonNull<SyncLink> {
+        NonNull::from(self).cast()
+    }
+
+    /// リストに属していれば`true`を返す。他のスレッドが外すことがあるので、結果はすぐに古くなりうる。
+    pub fn is_linked(&self) -> bool {
+        !self.list.load(Ordering::Acquire).is_null()
+    }
+
+    /// 属しているリストから外す。外したら`true`を返す。
+    ///
+    /// # Panics
+    /// 同じスレッドが持っている、属しているリストの`SyncListGuard`が`iter`などで要素を貸した後の場合
+    pub fn remove(self: Pin<&Self>) -> bool {
+        self.get_ref().detach()
+    }
+
+    // 属しているリストのロックを今のスレッドが持っていれば、そのリストを返す。
+    // ロックを持っている間は他のスレッドが`list`を書き換えないので、結果は古くならない
+    fn list_held_by_current_thread(&self) -> Option<&SyncList<T>> {
+        let list = self.list.load(Ordering::Acquire);
+        // ノードがドロップされるまでリストは生きている
+        let list = unsafe { list.as_ref()? };
+        list.lock.is_held_by_current_thread().then_some(list)
+    }
+
+    fn detach(&self) -> bool {
+        if let Some(list) = self.list_held_by_current_thread() {
+            assert!(!list.lent.load(Ordering::Relaxed), "the list has lent its elements through the current guard");
+            unsafe { self.link.unlink() };
+            self.list.store(ptr::null_mut(), Ordering::Release);
+            return true;
+        }
+        loop {
+            let list = self.list.load(Ordering::Acquire);
+            if list.is_null() {
+                return false;
+            }
+            // ノードがドロップされるまでリストは生きている
+            let list = unsafe { &*list };
+            list.lock.lock();
+            // ロックを取るまでの間に他のスレッドが外しているかもしれない
+            let attached = ptr::eq(self.list.load(Ordering::Relaxed), list);
+            if attached {
+                unsafe { self.link.unlink() };
+                self.list.store(ptr::null_mut(), Ordering::Release);
+            }
+            list.lock.unlock();
+            if attached {
+                return true;
+            }
+        }
+    }
+}
+
+impl<T> Deref for SyncNode<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T> Drop for SyncNode<'_, T> {
+    fn drop(&mut self) {
+        if self.list_held_by_current_thread().is_some_and(|list| list.lent.load(Ordering::Relaxed)) {
+            // パニックしても巻き戻しの途中で領域が解放されるので、ここで止めるしかない
+            eprintln!("a `SyncNode` was dropped while the current thread's guard may lend its data");
+            process::abort();
+        }
+        self.detach();
+    }
+}
+
+/// ロックで守られた、スレッド間で共有できる双方向リストの先頭
+///
+/// 操作は`lock`で得たガードを通して行う。番兵は自身を指すので、`Pin`で固定してから使う。
+///
+/// ノードはリストより先にドロップされる必要がある。
+/// ```compile_fail
+/// use linked_list::{SyncList, SyncNode};
+/// use std::pin::pin;
+///
+/// let node = pin!(SyncNode::new(1));
+/// let list = pin!(SyncList::new());
+/// list.as_ref().lock().push_back(node.as_ref());
+/// ```
+pub struct SyncList<T> {
+    lock: SpinLock,
+    // 先頭のノードを`next`、末尾のノードを`prev`で指す。初めてロックを取ったときに自身を指すようにする
+    sentinel: SyncLink,
+    // 今のガードが`iter`などで要素を貸したら`true`。ロックを取っている間だけ読み書きする
+    lent: AtomicBool,
+    _marker: PhantomData<*const T>,
+    _pinned: PhantomPinned,
+}
+
+unsafe impl<T: Sync> Send for SyncList<T> {}
+unsafe impl<T: Sync> Sync for SyncList<T> {}
+
+impl<T> Default for SyncList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SyncList<T> {
+    pub const fn new() -> Self {
+        Self {
+            lock: SpinLock::new(),
+            sentinel: SyncLink::new(),
+            lent: AtomicBool::new(false),
+            _marker: PhantomData,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    fn sentinel(&self) -> NonNull<SyncLink> {
+        NonNull::from(&self.sentinel)
+    }
+
+    /// ロックを取る。ロックはガードがドロップされるまで保持される。
+    ///
+    /// ガードより後に宣言したノードは、ガードより先にドロップされる。
+    /// そのときはロックを取り直さずにリストから外すので、次のように書いてよい。
+    /// ```
+    /// use linked_list::{SyncList, SyncNode};
+    /// use std::pin::pin;
+    ///
+    /// let list = pin!(SyncList::new());
+    /// let mut guard = list.as_ref().lock();
+    /// let node = pin!(SyncNode::new(1));
+    /// guard.push_back(node.as_ref());
+    /// ```
+    /// ただしガードが`iter`や`pop_front_with`で要素を貸した後にノードがドロップされると、プロセスを中断する。
+    ///
+    /// # Panics
+    /// 同じスレッドがすでにロックを取っている場合
+    pub fn lock<'l>(self: Pin<&'l Self>) -> SyncListGuard<'l, T> {
+        let list = self.get_ref();
+        list.lock.lock();
+        if list.sentinel.next() == NonNull::dangling() {
+            list.sentinel.next.store(list.sentinel(), Ordering::Relaxed);
+            list.sentinel.prev.store(list.sentinel(), Ordering::Relaxed);
+        }
+        SyncListGuard {
+            list,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for SyncList<T> {
+    fn drop(&mut self) {
+        // つないだノードはすでにドロップされているので、残っているのはリークされたノードだけ
+        let sentinel = self.sentinel();
+        let mut link = self.sentinel.next();
+        if link == NonNull::dangling() {
+            return;
+        }
+        while link != sentinel {
+            let node = unsafe { link.cast::<SyncNode<'_, T>>().as_ref() };
+            link = node.link.next();
+            node.list.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+}
+
+/// ロックを取った`SyncList`
+///
+/// ロックは取ったスレッドを覚えているので、`std::sync::MutexGuard`と同様に他のスレッドには送れない。
+/// ```compile_fail
+/// fn assert_send<T: Send>() {}
+/// assert_send::<linked_list::SyncListGuard<'static, i32>>();
+/// ```
+pub struct SyncListGuard<'l, T> {
+    list: &'l SyncList<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'l, T> SyncListGuard<'l, T> {
+    unsafe fn node_at(&self, link: NonNull<SyncLink>) -> &SyncNode<'l, T> {
+        link.cast().as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.sentinel.next() == self.list.sentinel()
+    }
+
+    /// O(n)
+    pub fn len(&self) -> usize {
+        self.links().count()
+    }
+
+    // `node`をこのリストのものにして`prev`と`next`の間につなぐ
+    fn link_between(&mut self, node: Pin<&SyncNode<'l, T>>, prev: NonNull<SyncLink>, next: NonNull<SyncLink>) {
+        let list = self.list as *const SyncList<T> as *mut SyncList<T>;
+        node.list
+            .compare_exchange(ptr::null_mut(), list, Ordering::Acquire, Ordering::Relaxed)
+            .expect("the node is already in a list");
+        unsafe { node.link.link_between(node.as_link(), prev, next) };
+    }
+
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn push_front(&mut self, node: Pin<&SyncNode<'l, T>>) {
+        let sentinel = self.list.sentinel();
+        self.link_between(node, sentinel, self.list.sentinel.next());
+    }
+
+    /// # Panics
+    /// `node`がすでにリストに属している場合
+    pub fn push_back(&mut self, node: Pin<&SyncNode<'l, T>>) {
+        let sentinel = self.list.sentinel();
+        self.link_between(node, self.list.sentinel.prev(), sentinel);
+    }
+
+    // `link`のノードを`f`に渡してから外す。外した後はノードに触れない
+    fn pop_with<R>(&mut self, link: NonNull<SyncLink>, f: impl FnOnce(&T) -> R) -> Option<R> {
+        if link == self.list.sentinel() {
+            return None;
+        }
+        let node = unsafe { self.node_at(link) };
+        self.list.lent.store(true, Ordering::Relaxed);
+        let ret = f(&node.data);
+        unsafe { node.link.unlink() };
+        node.list.store(ptr::null_mut(), Ordering::Release);
+        Some(ret)
+    }
+
+    /// 先頭のノードを外す。外したノードは他のスレッドがすぐにドロップしうるので、要素は外す前に`f`に渡す。
+    pub fn pop_front_with<R>(&mut self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.pop_with(self.list.sentinel.next(), f)
+    }
+
+    /// 末尾のノードを外す。外したノードは他のスレッドがすぐにドロップしうるので、要素は外す前に`f`に渡す。
+    pub fn pop_back_with<R>(&mut self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.pop_with(self.list.sentinel.prev(), f)
+    }
+
+    /// 先頭から順に要素を返すイテレータ
+    pub fn iter(&self) -> SyncIter<'_, T> {
+        self.list.lent.store(true, Ordering::Relaxed);
+        self.links()
+    }
+
+    // 要素を外に貸さずにたどる
+    fn links(&self) -> SyncIter<'_, T> {
+        SyncIter {
+            link: self.list.sentinel.next(),
+            sentinel: self.list.sentinel(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for SyncListGuard<'_, T> {
+    fn drop(&mut self) {
+        self.list.lent.store(false, Ordering::Relaxed);
+        self.list.lock.unlock();
+    }
+}
+
+/// `SyncListGuard::iter`が返すイテレータ
+///
+/// ガードを借用している間はロックが保持されるので、ノードが外されることはない。
+pub struct SyncIter<'g, T> {
+    link: NonNull<SyncLink>,
+    sentinel: NonNull<SyncLink>,
+    _marker: PhantomData<&'g T>,
+}
+
+impl<'g, T> Iterator for SyncIter<'g, T> {
+    type Item = &'g T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.link == self.sentinel {
+            return None;
+        }
+        let node = unsafe { self.link.cast::<SyncNode<'_, T>>().as_ref() };
+        self.link = node.link.next();
+        Some(&node.data)
+    }
+}
+
+impl<'g, T> IntoIterator for &'g SyncListGuard<'_, T> {
+    type Item = &'g T;
+    type IntoIter = SyncIter<'g, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{mem::ManuallyDrop, pin::pin};
+
+    #[test]
+    fn sync_list_test() {
+        let list = pin!(SyncList::new());
+        let list = list.as_ref();
+        let a = pin!(SyncNode::new(1));
+        let b = pin!(SyncNode::new(2));
+        {
+            let c = pin!(SyncNode::new(3));
+            let mut guard = list.lock();
+            assert!(guard.is_empty());
+            guard.push_back(b.as_ref());
+            guard.push_front(a.as_ref());
+            guard.push_back(c.as_ref());
+            assert_eq!(vec![1, 2, 3], guard.iter().copied().collect::<Vec<_>>());
+            assert_eq!(3, guard.len());
+            drop(guard);
+            assert!(c.is_linked());
+        }
+        assert_eq!(vec![1, 2], list.lock().iter().copied().collect::<Vec<_>>());
+        assert!(a.as_ref().remove());
+        assert!(!a.as_ref().remove());
+        assert!(!a.is_linked());
+
+        let mut guard = list.lock();
+        guard.push_back(a.as_ref());
+        assert_eq!(Some(2), guard.pop_front_with(|&x| x));
+        assert_eq!(Some(1), guard.pop_back_with(|&x| x));
+        assert_eq!(None, guard.pop_front_with(|&x| x));
+        assert!(guard.is_empty());
+        drop(guard);
+        assert!(!a.is_linked() && !b.is_linked());
+    }
+
+    #[test]
+    #[should_panic]
+    fn sync_push_linked_test() {
+        let list = pin!(SyncList::new());
+        let other = pin!(SyncList::new());
+        let a = pin!(SyncNode::new(1));
+        list.as_ref().lock().push_back(a.as_ref());
+        other.as_ref().lock().push_back(a.as_ref());
+    }
+
+    #[test]
+    fn sync_leak_test() {
+        // リークしたノードが残っていても、リストは問題なくドロップできる
+        let leaked;
+        {
+            let list = pin!(SyncList::new());
+            let a = pin!(SyncNode::new(1));
+            let node = Box::into_raw(Box::new(SyncNode::new(2)));
+            let mut guard = list.as_ref().lock();
+            guard.push_back(unsafe { Pin::new_unchecked(&*node) });
+            guard.push_back(a.as_ref());
+            drop(guard);
+            assert!(a.as_ref().remove());
+            leaked = node.cast::<()>();
+        }
+        // Miriのリーク検査に掛からないよう、ドロップ処理は飛ばして領域だけ解放する
+        drop(unsafe { Box::from_raw(leaked.cast::<ManuallyDrop<SyncNode<'_, i32>>>()) });
+    }
+
+    #[test]
+    fn sync_drop_locked_test() {
+        let list = pin!(SyncList::new());
+        let mut guard = list.as_ref().lock();
+        {
+            // ガードより後に宣言したノードは先にドロップされ、ロックを取り直さずに外れる
+            let a = pin!(SyncNode::new(1));
+            let b = pin!(SyncNode::new(2));
+            guard.push_back(a.as_ref());
+            guard.push_back(b.as_ref());
+            assert_eq!(2, guard.len());
+            assert!(b.as_ref().remove());
+            assert_eq!(1, guard.len());
+        }
+        assert!(guard.is_empty());
+        assert_eq!(None, guard.pop_front_with(|x| *x));
+    }
+
+    #[test]
+    #[should_panic]
+    fn sync_remove_lent_test() {
+        // パニックした後でリストがたどれるように、ノードの領域はリストより長く生かしておく
+        let node = ManuallyDrop::new(SyncNode::new(1));
+        let list = pin!(SyncList::new());
+        let mut guard = list.as_ref().lock();
+        guard.push_back(unsafe { Pin::new_unchecked(&*node) });
+        let first = guard.iter().next();
+        // 貸した参照が宙に浮くので、外さずにパニックする
+        unsafe { Pin::new_unchecked(&*node) }.remove();
+        assert_eq!(Some(&1), first);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sync_lock_twice_test() {
+        let list = pin!(SyncList::<i32>::new());
+        let _guard = list.as_ref().lock();
+        list.as_ref().lock();
+    }
+
+    #[test]
+    fn sync_stress_test() {
+        // Miriでは遅すぎるので減らす
+        const THREADS: usize = if cfg!(miri) { 2 } else { 8 };
+        const ROUNDS: usize = if cfg!(miri) { 30 } else { 2000 };
+        let list = pin!(SyncList::new());
+        let list = list.as_ref();
+        let popped = AtomicUsize::new(0);
+        let removed = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let (popped, removed) = (&popped, &removed);
+                s.spawn(move || {
+                    for i in 0..ROUNDS {
+                        let node = pin!(SyncNode::new(t * ROUNDS + i));
+                        let other = pin!(SyncNode::new(0));
+                        let mut guard = list.lock();
+                        if i % 2 == 0 {
+                            guard.push_back(node.as_ref());
+                        } else {
+                            guard.push_front(node.as_ref());
+                        }
+                        guard.push_back(other.as_ref());
+                        assert!(guard.len() >= 2);
+                        drop(guard);
+                        // 3で割って2余るときは、つないだままドロップする
+                        match i % 3 {
+                            0 if node.as_ref().remove() => {
+                                removed.fetch_add(1, Ordering::Relaxed);
+                            }
+                            1 if list.lock().pop_front_with(|_| ()).is_some() => {
+                                popped.fetch_add(1, Ordering::Relaxed);
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+        assert!(list.lock().is_empty());
+        assert!(popped.load(Ordering::Relaxed) > 0);
+        assert!(removed.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn sync_wait_queue_test() {
+        const THREADS: usize = if cfg!(miri) { 2 } else { 4 };
+        const ROUNDS: usize = if cfg!(miri) { 10 } else { 500 };
+        let list = pin!(SyncList::new());
+        let list = list.as_ref();
+        let woken = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for _ in 0..ROUNDS {
+                        let flag = pin!(SyncNode::new(AtomicBool::new(false)));
+                        list.lock().push_back(flag.as_ref());
+                        // 起こされるまで待つ
+                        while !flag.load(Ordering::Acquire) {
+                            thread::yield_now();
+                        }
+                    }
+                });
+            }
+            s.spawn(|| {
+                while woken.load(Ordering::Relaxed) < THREADS * ROUNDS {
+                    if list.lock().pop_front_with(|flag| flag.store(true, Ordering::Release)).is_some() {
+                        woken.fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        thread::yield_now();
+                    }
+                }
+            });
+        });
+        assert_eq!(THREADS * ROUNDS, woken.load(Ordering::Relaxed));
+        assert!(list.lock().is_empty());
+    }
+}
@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use segtree::{monoid::Sum, SegTree};
+
+const SIZES: [usize; 3] = [1 << 10, 1 << 16, (1 << 20) + 1];
+
+#[allow(dead_code)]
+#[path = "../src/test_util.rs"]
+mod test_util;
+use test_util::xorshift;
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    for n in SIZES {
+        let v = (0..n as u64).collect::<Vec<_>>();
+        group.bench_with_input(BenchmarkId::from_parameter(n), &v, |b, v| {
+            b.iter(|| SegTree::<Sum<u64>>::from_slice(black_box(v)))
+        });
+    }
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+    for n in SIZES {
+        let segtree = SegTree::<Sum<u64>>::from_slice(&(0..n as u64).collect::<Vec<_>>());
+        let mut rand = xorshift(0x9e3779b97f4a7c15);
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| {
+                let l = rand(n + 1);
+                let r = l + rand(n + 1 - l);
+                segtree.query(black_box(l..r))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    for n in SIZES {
+        let mut segtree = SegTree::<Sum<u64>>::from_slice(&(0..n as u64).collect::<Vec<_>>());
+        let mut rand = xorshift(0x2545f4914f6cdd1d);
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| {
+                let i = rand(n);
+                segtree.update(black_box(i), i as u64)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build, query, update);
+criterion_main!(benches);
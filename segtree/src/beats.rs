@@ -0,0 +1,341 @@
+use std::{cmp::Ordering, ops::RangeBounds};
+
+use crate::{to_range, BinTreeVec, Indexer};
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    // 和はオーバーフローするとラップする
+    sum: i64,
+    max1: i64,
+    // 2番目に大きい値
+    max2: Option<i64>,
+    max_cnt: usize,
+    min1: i64,
+    // 2番目に小さい値
+    min2: Option<i64>,
+    min_cnt: usize,
+    // 区間内の(パディングでない)要素数。0なら他のフィールドは意味を持たない
+    len: usize,
+    // 子に未適用の加算
+    add: i64,
+}
+
+// `None`を無限大として扱う
+fn min_some(a: Option<i64>, b: Option<i64>) -> Option<i64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl Node {
+    const EMPTY: Self = Self {
+        sum: 0,
+        max1: i64::MIN,
+        max2: None,
+        max_cnt: 0,
+        min1: i64::MAX,
+        min2: None,
+        min_cnt: 0,
+        len: 0,
+        add: 0,
+    };
+
+    const fn leaf(value: i64) -> Self {
+        Self {
+            sum: value,
+            max1: value,
+            max2: None,
+            max_cnt: 1,
+            min1: value,
+            min2: None,
+            min_cnt: 1,
+            len: 1,
+            add: 0,
+        }
+    }
+
+    fn merge(a: &Self, b: &Self) -> Self {
+        if a.len == 0 {
+            return Self { add: 0, ..*b };
+        }
+        if b.len == 0 {
+            return Self { add: 0, ..*a };
+        }
+        let (max1, max2, max_cnt) = match a.max1.cmp(&b.max1) {
+            Ordering::Less => (b.max1, Some(a.max1).max(b.max2), b.max_cnt),
+            Ordering::Greater => (a.max1, a.max2.max(Some(b.max1)), a.max_cnt),
+            Ordering::Equal => (a.max1, a.max2.max(b.max2), a.max_cnt + b.max_cnt),
+        };
+        let (min1, min2, min_cnt) = match a.min1.cmp(&b.min1) {
+            Ordering::Greater => (b.min1, min_some(Some(a.min1), b.min2), b.min_cnt),
+            Ordering::Less => (a.min1, min_some(a.min2, Some(b.min1)), a.min_cnt),
+            Ordering::Equal => (a.min1, min_some(a.min2, b.min2), a.min_cnt + b.min_cnt),
+        };
+        Self {
+            sum: a.sum.wrapping_add(b.sum),
+            max1,
+            max2,
+            max_cnt,
+            min1,
+            min2,
+            min_cnt,
+            len: a.len + b.len,
+            add: 0,
+        }
+    }
+
+    // `max2 < x < max1`のときに、最大値を`x`に下げる
+    fn chmin_top(&mut self, x: i64) {
+        self.sum = self.sum.wrapping_add(x.wrapping_sub(self.max1).wrapping_mul(self.max_cnt as i64));
+        if self.max1 == self.min1 {
+            self.min1 = x;
+        } else if Some(self.max1) == self.min2 {
+            self.min2 = Some(x);
+        }
+        self.max1 = x;
+    }
+
+    // `min1 < x < min2`のときに、最小値を`x`に上げる
+    fn chmax_top(&mut self, x: i64) {
+        self.sum = self.sum.wrapping_add(x.wrapping_sub(self.min1).wrapping_mul(self.min_cnt as i64));
+        if self.min1 == self.max1 {
+            self.max1 = x;
+        } else if Some(self.min1) == self.max2 {
+            self.max2 = Some(x);
+        }
+        self.min1 = x;
+    }
+
+    // 各要素を`lo..=hi`に収める。親が持っている範囲で呼ぶので、`chmin_top`と`chmax_top`の条件を満たす
+    fn clamp(&mut self, lo: i64, hi: i64) {
+        if hi < self.max1 {
+            self.chmin_top(hi);
+        }
+        if lo > self.min1 {
+            self.chmax_top(lo);
+        }
+    }
+
+    fn add_all(&mut self, x: i64) {
+        if self.len == 0 {
+            return;
+        }
+        self.sum = self.sum.wrapping_add(x.wrapping_mul(self.len as i64));
+        self.max1 += x;
+        self.max2 = self.max2.map(|v| v + x);
+        self.min1 += x;
+        self.min2 = self.min2.map(|v| v + x);
+        self.add += x;
+    }
+}
+
+/// Segment Tree Beats
+///
+/// 区間chmin、区間chmax、区間加算と、区間の和・最大値・最小値の取得ができる。
+/// 更新は償却O(log^2 n)、取得はO(log n)。
+/// 遅延している更新を取得時に子に伝播させるので、取得にも`&mut self`が必要。
+/// 和はオーバーフローするとラップする。加算で要素自体がオーバーフローしてはならない。
+#[derive(Debug, Clone)]
+pub struct SegTreeBeats {
+    tree: BinTreeVec<Node>,
+    len: usize,
+}
+
+enum Update {
+    Chmin(i64),
+    Chmax(i64),
+    Add(i64),
+}
+
+impl SegTreeBeats {
+    pub fn from_slice(slc: &[i64]) -> Self {
+        let len = slc.len();
+        let len_aligned = len.next_power_of_two();
+        let mut v = vec![Node::EMPTY; len_aligned * 2 - 1];
+        for (d, &s) in v[(len_aligned - 1)..].iter_mut().zip(slc) {
+            *d = Node::leaf(s);
+        }
+        for i in (0..(len_aligned - 1)).rev() {
+            let (ch1, ch2) = Indexer(i).child();
+            v[i] = Node::merge(&v[ch1.0], &v[ch2.0]);
+        }
+        Self {
+            tree: BinTreeVec(v),
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn len_aligned(&self) -> usize {
+        self.tree.len() / 2 + 1
+    }
+
+    fn push(&mut self, index: Indexer) {
+        let node = self.tree[index];
+        let (ch1, ch2) = index.child();
+        for ch in [ch1, ch2] {
+            let child = &mut self.tree[ch];
+            // 子の古い値にそのまま加算するとオーバーフローしうるので、加算前の値の範囲で先に切り詰める
+            if node.add != 0 {
+                child.clamp(node.min1.saturating_sub(node.add), node.max1.saturating_sub(node.add));
+                child.add_all(node.add);
+            }
+            child.clamp(node.min1, node.max1);
+        }
+        self.tree[index].add = 0;
+    }
+
+    fn pull(&mut self, index: Indexer) {
+        let (ch1, ch2) = index.child();
+        self.tree[index] = Node::merge(&self.tree[ch1], &self.tree[ch2]);
+    }
+
+    fn update_rec(&mut self, index: Indexer, node_l: usize, node_r: usize, l: usize, r: usize, update: &Update) {
+        if r <= node_l || node_r <= l {
+            return;
+        }
+        let node = &mut self.tree[index];
+        match *update {
+            Update::Chmin(x) if node.max1 <= x => return,
+            Update::Chmax(x) if node.min1 >= x => return,
+            _ => {}
+        }
+        if l <= node_l && node_r <= r {
+            match *update {
+                // 葉は2番目の値を持たないので、ここで必ず止まる
+                Update::Chmin(x) if node.max2.is_none_or(|m| m < x) => return node.chmin_top(x),
+                Update::Chmax(x) if node.min2.is_none_or(|m| m > x) => return node.chmax_top(x),
+                Update::Add(x) => return node.add_all(x),
+                _ => {}
+            }
+        }
+        self.push(index);
+        let mid = (node_l + node_r) / 2;
+        let (ch1, ch2) = index.child();
+        self.update_rec(ch1, node_l, mid, l, r, update);
+        self.update_rec(ch2, mid, node_r, l, r, update);
+        self.pull(index);
+    }
+
+    fn update<R: RangeBounds<usize>>(&mut self, range: R, update: Update) {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        if l < r {
+            self.update_rec(Indexer(0), 0, self.len_aligned(), l, r, &update);
+        }
+    }
+
+    /// 区間の各要素を`min(a_i, x)`にする。
+    pub fn chmin<R: RangeBounds<usize>>(&mut self, range: R, x: i64) {
+        self.update(range, Update::Chmin(x));
+    }
+
+    /// 区間の各要素を`max(a_i, x)`にする。
+    pub fn chmax<R: RangeBounds<usize>>(&mut self, range: R, x: i64) {
+        self.update(range, Update::Chmax(x));
+    }
+
+    pub fn add<R: RangeBounds<usize>>(&mut self, range: R, x: i64) {
+        self.update(range, Update::Add(x));
+    }
+
+    fn query_rec(&mut self, index: Indexer, node_l: usize, node_r: usize, l: usize, r: usize) -> Node {
+        if r <= node_l || node_r <= l {
+            return Node::EMPTY;
+        }
+        if l <= node_l && node_r <= r {
+            return self.tree[index];
+        }
+        self.push(index);
+        let mid = (node_l + node_r) / 2;
+        let (ch1, ch2) = index.child();
+        Node::merge(
+            &self.query_rec(ch1, node_l, mid, l, r),
+            &self.query_rec(ch2, mid, node_r, l, r),
+        )
+    }
+
+    fn query<R: RangeBounds<usize>>(&mut self, range: R) -> Node {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        if l == r {
+            return Node::EMPTY;
+        }
+        self.query_rec(Indexer(0), 0, self.len_aligned(), l, r)
+    }
+
+    pub fn query_sum<R: RangeBounds<usize>>(&mut self, range: R) -> i64 {
+        self.query(range).sum
+    }
+
+    /// 区間が空のときは`i64::MIN`を返す。
+    pub fn query_max<R: RangeBounds<usize>>(&mut self, range: R) -> i64 {
+        self.query(range).max1
+    }
+
+    /// 区間が空のときは`i64::MAX`を返す。
+    pub fn query_min<R: RangeBounds<usize>>(&mut self, range: R) -> i64 {
+        self.query(range).min1
+    }
+}
+
+#[test]
+fn beats_test() {
+    let mut rand = crate::test_util::xorshift(0x9e3779b97f4a7c15);
+
+    for n in [1, 2, 3, 7, 8, 9, 31] {
+        let mut naive: Vec<i64> = (0..n).map(|_| rand(200) as i64 - 100).collect();
+        let mut beats = SegTreeBeats::from_slice(&naive);
+        for _ in 0..2000 {
+            let l = rand(n + 1);
+            let r = l + rand(n + 1 - l);
+            let x = rand(200) as i64 - 100;
+            match rand(4) {
+                0 => {
+                    beats.chmin(l..r, x);
+                    naive[l..r].iter_mut().for_each(|v| *v = (*v).min(x));
+                }
+                1 => {
+                    beats.chmax(l..r, x);
+                    naive[l..r].iter_mut().for_each(|v| *v = (*v).max(x));
+                }
+                2 => {
+                    beats.add(l..r, x / 10);
+                    naive[l..r].iter_mut().for_each(|v| *v += x / 10);
+                }
+                _ => {
+                    assert_eq!(naive[l..r].iter().sum::<i64>(), beats.query_sum(l..r));
+                    assert_eq!(naive[l..r].iter().copied().max().unwrap_or(i64::MIN), beats.query_max(l..r));
+                    assert_eq!(naive[l..r].iter().copied().min().unwrap_or(i64::MAX), beats.query_min(l..r));
+                }
+            }
+        }
+        assert_eq!(naive.iter().sum::<i64>(), beats.query_sum(..));
+    }
+}
+
+#[test]
+fn beats_extreme_test() {
+    let mut beats = SegTreeBeats::from_slice(&[3, -7, 5]);
+    beats.chmin(.., i64::MIN);
+    assert_eq!(i64::MIN, beats.query_max(..));
+    beats.chmax(.., i64::MAX);
+    assert_eq!(i64::MAX, beats.query_min(..));
+    assert_eq!(i64::MAX.wrapping_mul(3), beats.query_sum(..));
+
+    let mut beats = SegTreeBeats::from_slice(&[i64::MIN, 5]);
+    beats.add(.., 10);
+    beats.chmin(.., i64::MIN + 5);
+    assert_eq!([i64::MIN + 5, i64::MIN + 5], [beats.query_max(..1), beats.query_min(1..)]);
+    beats.chmax(1.., i64::MIN + 7);
+    assert_eq!([i64::MIN + 5, i64::MIN + 7], [beats.query_max(..1), beats.query_min(1..)]);
+    assert_eq!((i64::MIN + 5).wrapping_add(i64::MIN + 7), beats.query_sum(..));
+}
@@ -0,0 +1,150 @@
+use std::{
+    fmt,
+    ops::{Deref, RangeBounds},
+};
+
+use crate::{monoid::Monoid, to_range, BinTreeVec, Indexer};
+
+/// 要素数を2の冪に揃えないセグメント木
+///
+/// 要素数`n`に対して`2n - 1`個のノードしか使わない。
+/// 根に近いノードは添字が連続しない葉をまとめることがあるが、
+/// `query`はそのようなノードを使わないので非可換なモノイドでも正しく動く。
+pub struct CompactSegTree<M: Monoid> {
+    tree: BinTreeVec<M::Item>,
+}
+
+impl<M: Monoid> fmt::Debug for CompactSegTree<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompactSegTree").field("tree", &self.tree).finish()
+    }
+}
+
+impl<M: Monoid> Clone for CompactSegTree<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<M: Monoid> CompactSegTree<M>
+where
+    M::Item: Clone,
+{
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        Self::from(slc.to_vec())
+    }
+}
+
+impl<M: Monoid> From<Vec<M::Item>> for CompactSegTree<M> {
+    /// `v`の領域をそのまま使って木を構築する。
+    fn from(mut v: Vec<M::Item>) -> Self {
+        let len = v.len();
+        if len == 0 {
+            return Self { tree: BinTreeVec(v) };
+        }
+        v.reserve_exact(len - 1);
+        v.extend(std::iter::repeat_with(M::identity).take(len - 1));
+        v.rotate_right(len - 1);
+
+        let mut segtree = Self { tree: BinTreeVec(v) };
+        for i in (0..(len - 1)).rev() {
+            segtree.evaluate(Indexer(i));
+        }
+        segtree
+    }
+}
+
+impl<M: Monoid> FromIterator<M::Item> for CompactSegTree<M> {
+    fn from_iter<I: IntoIterator<Item = M::Item>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<M: Monoid> CompactSegTree<M> {
+    fn offset(&self) -> usize {
+        self.tree.len() / 2
+    }
+
+    fn evaluate(&mut self, index: Indexer) {
+        let val = {
+            let (ch1, ch2) = index.child();
+            M::op(&self.tree[ch1], &self.tree[ch2])
+        };
+        self.tree[index] = val;
+    }
+
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> M::Item {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r && r <= self.len(), "range {l}..{r} out of bounds for length {}", self.len());
+
+        let mut left = Indexer(self.offset() + l);
+        // 右端は含まない
+        let mut right = Indexer(self.offset() + r);
+        let mut result1 = M::identity();
+        let mut result2 = M::identity();
+        while left < right {
+            if left.is_right() {
+                result1 = M::op(&result1, &self.tree[left]);
+                left += 1;
+            }
+            if right.is_right() {
+                right -= 1;
+                result2 = M::op(&self.tree[right], &result2);
+            }
+            left = left.parent();
+            right = right.parent();
+        }
+        M::op(&result1, &result2)
+    }
+
+    pub fn update(&mut self, index: usize, value: M::Item) {
+        assert!(index < self.len(), "index {index} out of bounds for length {}", self.len());
+        let mut idx = Indexer(self.offset() + index);
+        self.tree[idx] = value;
+        while !idx.is_root() {
+            idx = idx.parent();
+            self.evaluate(idx);
+        }
+    }
+}
+
+impl<M: Monoid> Deref for CompactSegTree<M> {
+    type Target = [M::Item];
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree.0[self.offset()..]
+    }
+}
+
+#[test]
+fn compact_test() {
+    use crate::monoid::Affine;
+
+    let naive = crate::test_util::affine_fold;
+    for n in [0, 1, 2, 3, 5, 6, 7, 8, 9, 13] {
+        let mut v = (0..n).map(|i| (i % 3 - 1, i * 2 - 3)).collect::<Vec<_>>();
+        let mut segtree = v.iter().copied().collect::<CompactSegTree<Affine<i64>>>();
+        assert_eq!((2 * n as usize).saturating_sub(1), segtree.tree.len());
+        for step in 0..3 {
+            if n > 0 {
+                let i = (step * 7) % n as usize;
+                v[i] = (2, step as i64);
+                segtree.update(i, v[i]);
+            }
+            assert_eq!(&v[..], &segtree[..]);
+            for l in 0..=v.len() {
+                for r in l..=v.len() {
+                    assert_eq!(naive(&v[l..r]), segtree.query(l..r));
+                }
+            }
+        }
+    }
+}
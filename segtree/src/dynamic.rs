@@ -0,0 +1,311 @@
+use std::{
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::monoid::Monoid;
+
+/// `DynamicSegTree`の添字として使える整数型
+pub trait Coord: Copy {
+    const MIN: i128;
+    const MAX: i128;
+
+    fn to_i128(self) -> i128;
+}
+
+macro_rules! impl_coord {
+    ($($t:ty),*) => {$(
+        impl Coord for $t {
+            const MIN: i128 = <$t>::MIN as i128;
+            const MAX: i128 = <$t>::MAX as i128;
+
+            fn to_i128(self) -> i128 {
+                self as i128
+            }
+        }
+    )*};
+}
+
+impl_coord!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// ノード数の上限を超えてノードを確保しようとしたときのエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError {
+    pub limit: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node limit {} exceeded", self.limit)
+    }
+}
+
+impl Error for CapacityError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NodeId(usize);
+
+const ROOT: NodeId = NodeId(0);
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: T,
+    child: [Option<NodeId>; 2],
+}
+
+/// 動的セグメント木
+///
+/// 値が一度も更新されていない区間のノードは作らないので、添字の範囲が`i64`や`u64`全体でも使える。
+/// 一回の更新で作られるノードは高々`log(範囲の幅) + 1`個。
+pub struct DynamicSegTree<M: Monoid, K: Coord = i64> {
+    nodes: Vec<Node<M::Item>>,
+    // 添字の範囲 (半開区間)
+    lo: i128,
+    hi: i128,
+    limit: Option<usize>,
+    _marker: PhantomData<fn(K)>,
+}
+
+impl<M: Monoid, K: Coord> fmt::Debug for DynamicSegTree<M, K>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicSegTree")
+            .field("nodes", &self.nodes)
+            .field("lo", &self.lo)
+            .field("hi", &self.hi)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl<M: Monoid, K: Coord> Clone for DynamicSegTree<M, K>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            lo: self.lo,
+            hi: self.hi,
+            limit: self.limit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn to_range_i128<K: Coord, R: RangeBounds<K>>(range: &R) -> (i128, i128) {
+    let l = match range.start_bound() {
+        Bound::Excluded(&l) => l.to_i128() + 1,
+        Bound::Included(&l) => l.to_i128(),
+        Bound::Unbounded => K::MIN,
+    };
+    let r = match range.end_bound() {
+        Bound::Excluded(&r) => r.to_i128(),
+        Bound::Included(&r) => r.to_i128() + 1,
+        Bound::Unbounded => K::MAX + 1,
+    };
+    (l, r)
+}
+
+impl<M: Monoid, K: Coord> DynamicSegTree<M, K> {
+    /// 添字の範囲が`domain`で、すべての値が単位元である木を作る。
+    pub fn new<R: RangeBounds<K>>(domain: R) -> Self {
+        let (lo, hi) = to_range_i128(&domain);
+        assert!(lo < hi, "domain must not be empty");
+        Self {
+            nodes: vec![Node {
+                value: M::identity(),
+                child: [None, None],
+            }],
+            lo,
+            hi,
+            limit: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// ノード数が`limit`を超えないような木を作る。根の分として`limit`は1以上である必要がある。
+    pub fn with_node_limit<R: RangeBounds<K>>(domain: R, limit: usize) -> Self {
+        assert!(limit >= 1, "limit must be at least 1");
+        let mut tree = Self::new(domain);
+        tree.nodes.reserve_exact(limit - 1);
+        tree.limit = Some(limit);
+        tree
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn node_limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// `index`番目の値を`value`にする。ノード数の上限を超える場合は何もせずにエラーを返す。
+    pub fn try_update(&mut self, index: K, value: M::Item) -> Result<(), CapacityError> {
+        let index = index.to_i128();
+        assert!(self.lo <= index && index < self.hi, "index {index} out of domain {}..{}", self.lo, self.hi);
+
+        if let Some(limit) = self.limit {
+            let (mut node, mut l, mut r) = (Some(ROOT), self.lo, self.hi);
+            let mut missing = 0;
+            while r - l > 1 {
+                let mid = l + (r - l) / 2;
+                let dir = index >= mid;
+                if dir {
+                    l = mid;
+                } else {
+                    r = mid;
+                }
+                node = node.and_then(|n| self.nodes[n.0].child[dir as usize]);
+                if node.is_none() {
+                    missing += 1;
+                }
+            }
+            if self.nodes.len() + missing > limit {
+                return Err(CapacityError { limit });
+            }
+        }
+
+        let (mut node, mut l, mut r) = (ROOT, self.lo, self.hi);
+        let mut path = vec![ROOT];
+        while r - l > 1 {
+            let mid = l + (r - l) / 2;
+            let dir = index >= mid;
+            if dir {
+                l = mid;
+            } else {
+                r = mid;
+            }
+            node = match self.nodes[node.0].child[dir as usize] {
+                Some(next) => next,
+                None => {
+                    self.nodes.push(Node {
+                        value: M::identity(),
+                        child: [None, None],
+                    });
+                    let next = NodeId(self.nodes.len() - 1);
+                    self.nodes[node.0].child[dir as usize] = Some(next);
+                    next
+                }
+            };
+            path.push(node);
+        }
+        self.nodes[node.0].value = value;
+
+        let identity = M::identity();
+        for &node in path.iter().rev().skip(1) {
+            let [ch1, ch2] = self.nodes[node.0].child.map(|c| c.map_or(&identity, |c| &self.nodes[c.0].value));
+            self.nodes[node.0].value = M::op(ch1, ch2);
+        }
+        Ok(())
+    }
+
+    /// `index`番目の値を`value`にする。
+    ///
+    /// # Panics
+    /// ノード数の上限を超える場合
+    pub fn update(&mut self, index: K, value: M::Item) {
+        if let Err(e) = self.try_update(index, value) {
+            panic!("{e}");
+        }
+    }
+}
+
+impl<M: Monoid, K: Coord> DynamicSegTree<M, K>
+where
+    M::Item: Clone,
+{
+    fn query_rec(&self, node: Option<NodeId>, node_l: i128, node_r: i128, l: i128, r: i128) -> M::Item {
+        let Some(node) = node else {
+            return M::identity();
+        };
+        if r <= node_l || node_r <= l {
+            return M::identity();
+        }
+        let node = &self.nodes[node.0];
+        if l <= node_l && node_r <= r {
+            return node.value.clone();
+        }
+        let mid = node_l + (node_r - node_l) / 2;
+        M::op(
+            &self.query_rec(node.child[0], node_l, mid, l, r),
+            &self.query_rec(node.child[1], mid, node_r, l, r),
+        )
+    }
+
+    /// 区間`range`の集約値を返す。区間が空の場合や添字の範囲と重ならない場合は`M::identity()`を返す。
+    ///
+    /// # Panics
+    /// 始端が終端より大きい場合 (`SegTree::query`と同様)
+    pub fn query<R: RangeBounds<K>>(&self, range: R) -> M::Item {
+        let (l, r) = to_range_i128(&range);
+        assert!(l <= r, "range start {l} is greater than end {r}");
+        let (l, r) = (l.max(self.lo), r.min(self.hi));
+        if l >= r {
+            return M::identity();
+        }
+        self.query_rec(Some(ROOT), self.lo, self.hi, l, r)
+    }
+
+    pub fn get(&self, index: K) -> M::Item {
+        self.query(index..=index)
+    }
+}
+
+#[test]
+fn dynamic_test() {
+    use crate::monoid::{Max, Sum};
+
+    let mut tree = DynamicSegTree::<Sum<i64>>::new(-1_000_000_000_000_000_000..=1_000_000_000_000_000_000);
+    let points = [
+        (-1_000_000_000_000_000_000, 3),
+        (-5, 7),
+        (0, 11),
+        (12_345_678_901_234, -4),
+        (1_000_000_000_000_000_000, 100),
+    ];
+    for &(i, v) in &points {
+        tree.update(i, v);
+    }
+    assert_eq!(117, tree.query(..));
+    assert_eq!(18, tree.query(-5..=0));
+    assert_eq!(7, tree.query(-5..0));
+    assert_eq!(96, tree.query(1..));
+    assert_eq!(0, tree.query(1..12_345_678_901_234));
+    assert_eq!(11, tree.get(0));
+    tree.update(0, 1);
+    assert_eq!(107, tree.query(..));
+    assert!(tree.node_count() < 5 * 62);
+
+    let mut full = DynamicSegTree::<Max<u64>, u64>::new(..);
+    full.update(u64::MAX, 5);
+    full.update(0, 3);
+    assert_eq!(5, full.query(..));
+    assert_eq!(3, full.query(..u64::MAX));
+
+    let mut limited = DynamicSegTree::<Sum<i32>, u32>::with_node_limit(0..16, 8);
+    limited.update(0, 1);
+    assert_eq!(5, limited.node_count());
+    assert_eq!(Ok(()), limited.try_update(1, 2));
+    assert_eq!(Err(CapacityError { limit: 8 }), limited.try_update(15, 3));
+    assert_eq!(6, limited.node_count());
+    assert_eq!(3, limited.query(..));
+}
+
+#[test]
+#[should_panic(expected = "range start 3 is greater than end -2")]
+fn dynamic_inverted_range_test() {
+    use crate::monoid::Sum;
+
+    let mut tree = DynamicSegTree::<Sum<i64>>::new(-10..10);
+    tree.update(0, 1);
+    assert_eq!(0, tree.query(3..3));
+    assert_eq!(0, tree.query(20..30));
+    #[allow(clippy::reversed_empty_ranges)]
+    tree.query(3..-2);
+}
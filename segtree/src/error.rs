@@ -0,0 +1,36 @@
+use std::{error::Error, fmt};
+
+/// 演算結果が表現できる範囲を超えたことを表すエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError;
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("arithmetic overflow")
+    }
+}
+
+impl Error for OverflowError {}
+
+/// `SegTree`の範囲外アクセスなどを表すエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegTreeError {
+    /// 添字または区間の終端が要素数を超えている
+    OutOfBounds { index: usize, len: usize },
+    /// 区間の始端が終端より大きい
+    InvertedRange { start: usize, end: usize },
+    /// 区間が空
+    EmptyRange,
+}
+
+impl fmt::Display for SegTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { index, len } => write!(f, "index {index} out of bounds for length {len}"),
+            Self::InvertedRange { start, end } => write!(f, "range start {start} is greater than end {end}"),
+            Self::EmptyRange => f.write_str("range is empty"),
+        }
+    }
+}
+
+impl Error for SegTreeError {}
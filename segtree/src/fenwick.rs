@@ -0,0 +1,198 @@
+use std::ops::{AddAssign, Mul, Neg, RangeBounds, Sub};
+
+use crate::to_range;
+
+/// Fenwick木 (Binary Indexed Tree)
+///
+/// 一点加算と接頭辞和がO(log n)でできる。`SegTree`と違いサイズを2の冪に揃えないので、
+/// 要素数と同じだけのメモリしか使わない。
+#[derive(Debug, Clone)]
+pub struct Fenwick<T> {
+    // 1-indexedで`i`番目のノードを`data[i - 1]`に持つ
+    data: Vec<T>,
+}
+
+impl<T: Copy + Default + AddAssign> Fenwick<T> {
+    pub fn new(len: usize) -> Self {
+        Self {
+            data: vec![T::default(); len],
+        }
+    }
+
+    pub fn from_slice(slc: &[T]) -> Self {
+        let mut data = slc.to_vec();
+        let len = data.len();
+        for i in 1..=len {
+            let j = i + (i & i.wrapping_neg());
+            if j <= len {
+                let val = data[i - 1];
+                data[j - 1] += val;
+            }
+        }
+        Self { data }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn add(&mut self, index: usize, delta: T) {
+        assert!(index < self.len(), "index {index} out of bounds for length {}", self.len());
+        let mut i = index + 1;
+        while i <= self.len() {
+            self.data[i - 1] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// `0..r`の和
+    pub fn prefix_sum(&self, r: usize) -> T {
+        assert!(r <= self.len(), "index {r} out of bounds for length {}", self.len());
+        let mut result = T::default();
+        let mut i = r;
+        while i > 0 {
+            result += self.data[i - 1];
+            i &= i - 1;
+        }
+        result
+    }
+}
+
+impl<T: Copy + Default + AddAssign + Sub<Output = T>> Fenwick<T> {
+    pub fn range_sum<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r, "range start {l} is greater than end {r}");
+        self.prefix_sum(r) - self.prefix_sum(l)
+    }
+}
+
+impl<T: Copy + Default + AddAssign + PartialOrd> Fenwick<T> {
+    /// `self.prefix_sum(i + 1) >= target`となる最小の`i`を返す。存在しなければ`self.len()`を返す。
+    ///
+    /// すべての要素が非負である必要がある。
+    pub fn lower_bound(&self, target: T) -> usize {
+        let len = self.len();
+        if len == 0 {
+            return 0;
+        }
+        let mut pos = 0;
+        let mut acc = T::default();
+        let mut step = 1 << len.ilog2();
+        while step > 0 {
+            if pos + step <= len {
+                let mut tmp = acc;
+                tmp += self.data[pos + step - 1];
+                if tmp < target {
+                    pos += step;
+                    acc = tmp;
+                }
+            }
+            step >>= 1;
+        }
+        pos
+    }
+}
+
+/// 区間加算と区間和ができるFenwick木
+///
+/// 2本の`Fenwick`を使って、`x`を`l..r`に加算したときの接頭辞和`x * (p - l)`を
+/// `p`の一次式として管理する。
+#[derive(Debug, Clone)]
+pub struct RangeFenwick<T> {
+    coef: Fenwick<T>,
+    constant: Fenwick<T>,
+}
+
+impl<T> RangeFenwick<T>
+where
+    T: Copy + Default + AddAssign + Sub<Output = T> + Mul<Output = T> + Neg<Output = T> + TryFrom<usize>,
+{
+    pub fn new(len: usize) -> Self {
+        Self {
+            coef: Fenwick::new(len),
+            constant: Fenwick::new(len),
+        }
+    }
+
+    pub fn from_slice(slc: &[T]) -> Self {
+        let neg = slc.iter().map(|&x| -x).collect::<Vec<_>>();
+        Self {
+            coef: Fenwick::new(slc.len()),
+            constant: Fenwick::from_slice(&neg),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.coef.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coef.is_empty()
+    }
+
+    fn convert(index: usize) -> T {
+        T::try_from(index)
+            .ok()
+            .expect("index does not fit in the value type")
+    }
+
+    // `index..`に`x`を足す
+    fn add_suffix(&mut self, index: usize, x: T) {
+        if index < self.len() {
+            self.coef.add(index, x);
+            self.constant.add(index, x * Self::convert(index));
+        }
+    }
+
+    pub fn add<R: RangeBounds<usize>>(&mut self, range: R, x: T) {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r && r <= self.len(), "range {l}..{r} out of bounds for length {}", self.len());
+        self.add_suffix(l, x);
+        self.add_suffix(r, -x);
+    }
+
+    pub fn prefix_sum(&self, r: usize) -> T {
+        self.coef.prefix_sum(r) * Self::convert(r) - self.constant.prefix_sum(r)
+    }
+
+    pub fn range_sum<R: RangeBounds<usize>>(&self, range: R) -> T {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r, "range start {l} is greater than end {r}");
+        self.prefix_sum(r) - self.prefix_sum(l)
+    }
+}
+
+#[test]
+fn fenwick_test() {
+    let s = [3u32, 0, 4, 1, 5, 9, 2, 6];
+    let mut fenwick = Fenwick::from_slice(&s);
+    assert_eq!(30, fenwick.prefix_sum(8));
+    assert_eq!(10, fenwick.range_sum(2..5));
+    assert_eq!(15, fenwick.range_sum(3..=5));
+    assert_eq!(0, fenwick.range_sum(4..4));
+    for target in 0..=31 {
+        let expected = (0..s.len())
+            .find(|&i| s[..=i].iter().sum::<u32>() >= target)
+            .unwrap_or(s.len());
+        assert_eq!(expected, fenwick.lower_bound(target));
+    }
+    fenwick.add(1, 7);
+    assert_eq!(10, fenwick.prefix_sum(2));
+    assert_eq!(1, fenwick.lower_bound(4));
+
+    let mut naive = vec![1i64, -2, 3, -4, 5, -6, 7];
+    let mut range = RangeFenwick::from_slice(&naive);
+    for (l, r, x) in [(0, 3, 2), (2, 7, -1), (4, 4, 100), (5, 6, 10), (0, 7, 3)] {
+        range.add(l..r, x);
+        naive[l..r].iter_mut().for_each(|v| *v += x);
+        for l in 0..=naive.len() {
+            for r in l..=naive.len() {
+                assert_eq!(naive[l..r].iter().sum::<i64>(), range.range_sum(l..r));
+            }
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use std::{fmt, ops::Range};
+
+use crate::{
+    lazy::LazySegTree,
+    monoid::{Act, Monoid, Rev},
+    SegTree,
+};
+
+/// 重軽分解
+///
+/// 根付き木の頂点に、各heavy pathと各部分木がそれぞれ連続する区間になるような位置を割り当てる。
+#[derive(Debug, Clone)]
+pub struct Hld {
+    parent: Vec<usize>,
+    depth: Vec<usize>,
+    // 頂点が属するheavy pathの先頭 (最も根に近い頂点)
+    head: Vec<usize>,
+    pos: Vec<usize>,
+    // 部分木の区間の終端 (含まない)
+    end: Vec<usize>,
+}
+
+impl Hld {
+    /// 無向木の隣接リスト`adj`を`root`を根として分解する。
+    ///
+    /// # Panics
+    /// `adj`が連結でない場合
+    pub fn new(adj: &[Vec<usize>], root: usize) -> Self {
+        let n = adj.len();
+        let mut parent = vec![usize::MAX; n];
+        let mut depth = vec![0; n];
+        let mut order = Vec::with_capacity(n);
+        if n > 0 {
+            parent[root] = root;
+            let mut stack = vec![root];
+            while let Some(v) = stack.pop() {
+                order.push(v);
+                for &u in &adj[v] {
+                    if parent[u] == usize::MAX {
+                        parent[u] = v;
+                        depth[u] = depth[v] + 1;
+                        stack.push(u);
+                    }
+                }
+            }
+        }
+        assert_eq!(n, order.len(), "the graph is not connected");
+
+        let mut size = vec![1; n];
+        let mut heavy = vec![usize::MAX; n];
+        for &v in order.iter().rev() {
+            if v != root {
+                size[parent[v]] += size[v];
+            }
+        }
+        for &v in &order {
+            if v != root && (heavy[parent[v]] == usize::MAX || size[heavy[parent[v]]] < size[v]) {
+                heavy[parent[v]] = v;
+            }
+        }
+
+        // heavyな子を最後に積むことで、heavyな子がすぐ次の位置になる
+        let mut head = vec![root; n];
+        let mut pos = vec![0; n];
+        let mut end = vec![0; n];
+        let mut next = 0;
+        let mut stack = if n > 0 { vec![root] } else { Vec::new() };
+        while let Some(v) = stack.pop() {
+            pos[v] = next;
+            end[v] = next + size[v];
+            next += 1;
+            for &u in &adj[v] {
+                if parent[u] == v && u != root && u != heavy[v] {
+                    head[u] = u;
+                    stack.push(u);
+                }
+            }
+            if heavy[v] != usize::MAX {
+                head[heavy[v]] = head[v];
+                stack.push(heavy[v]);
+            }
+        }
+
+        Self {
+            parent,
+            depth,
+            head,
+            pos,
+            end,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos.is_empty()
+    }
+
+    /// 頂点`v`に割り当てられた位置
+    pub fn pos(&self, v: usize) -> usize {
+        self.pos[v]
+    }
+
+    /// 頂点`v`の部分木に対応する区間
+    pub fn subtree_range(&self, v: usize) -> Range<usize> {
+        self.pos[v]..self.end[v]
+    }
+
+    /// 最小共通祖先
+    pub fn lca(&self, mut u: usize, mut v: usize) -> usize {
+        while self.head[u] != self.head[v] {
+            if self.depth[self.head[u]] > self.depth[self.head[v]] {
+                u = self.parent[self.head[u]];
+            } else {
+                v = self.parent[self.head[v]];
+            }
+        }
+        if self.depth[u] < self.depth[v] {
+            u
+        } else {
+            v
+        }
+    }
+
+    /// `u`から`v`へのパスを区間に分ける。
+    ///
+    /// 1つ目は`u`から上る部分で、各区間を位置の降順にたどる。
+    /// 2つ目は`v`へ下る部分で、各区間を位置の昇順にたどる。どちらもたどる順に並ぶ。
+    pub fn path_ranges(&self, mut u: usize, mut v: usize) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+        let (mut up, mut down) = (Vec::new(), Vec::new());
+        while self.head[u] != self.head[v] {
+            if self.depth[self.head[u]] >= self.depth[self.head[v]] {
+                up.push(self.pos[self.head[u]]..(self.pos[u] + 1));
+                u = self.parent[self.head[u]];
+            } else {
+                down.push(self.pos[self.head[v]]..(self.pos[v] + 1));
+                v = self.parent[self.head[v]];
+            }
+        }
+        if self.pos[u] >= self.pos[v] {
+            up.push(self.pos[v]..(self.pos[u] + 1));
+        } else {
+            down.push(self.pos[u]..(self.pos[v] + 1));
+        }
+        down.reverse();
+        (up, down)
+    }
+
+    fn arrange<T: Clone>(&self, values: &[T]) -> Vec<T> {
+        assert_eq!(self.len(), values.len(), "the number of values must equal the number of vertices");
+        let mut v = values.to_vec();
+        for (i, x) in values.iter().enumerate() {
+            v[self.pos[i]] = x.clone();
+        }
+        v
+    }
+}
+
+/// 頂点に値を持つ木の上のセグメント木
+///
+/// パスの集約は`u`から`v`への順に行うので、非可換なモノイドでも正しい。
+/// そのために逆順に集約する木も持つ。
+pub struct HldSegTree<M: Monoid> {
+    hld: Hld,
+    forward: SegTree<M>,
+    backward: SegTree<Rev<M>>,
+}
+
+impl<M: Monoid> fmt::Debug for HldSegTree<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HldSegTree").field("hld", &self.hld).field("forward", &self.forward).finish()
+    }
+}
+
+impl<M: Monoid> Clone for HldSegTree<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            hld: self.hld.clone(),
+            forward: self.forward.clone(),
+            backward: self.backward.clone(),
+        }
+    }
+}
+
+impl<M: Monoid> HldSegTree<M>
+where
+    M::Item: Clone,
+{
+    /// `values[v]`を頂点`v`の値とする。
+    pub fn new(adj: &[Vec<usize>], root: usize, values: &[M::Item]) -> Self {
+        let hld = Hld::new(adj, root);
+        let leaves = hld.arrange(values);
+        Self {
+            forward: SegTree::from_slice(&leaves),
+            backward: SegTree::from(leaves),
+            hld,
+        }
+    }
+
+    pub fn update(&mut self, v: usize, value: M::Item) {
+        let i = self.hld.pos(v);
+        self.forward.update(i, value.clone());
+        self.backward.update(i, value);
+    }
+}
+
+impl<M: Monoid> HldSegTree<M> {
+    pub fn hld(&self) -> &Hld {
+        &self.hld
+    }
+
+    pub fn get(&self, v: usize) -> &M::Item {
+        &self.forward[self.hld.pos(v)]
+    }
+
+    /// `u`から`v`へのパス上の頂点の値を、`u`の側から順に集約する。
+    pub fn path_query(&self, u: usize, v: usize) -> M::Item {
+        let (up, down) = self.hld.path_ranges(u, v);
+        let up = up.into_iter().map(|r| self.backward.query(r));
+        let down = down.into_iter().map(|r| self.forward.query(r));
+        up.chain(down).fold(M::identity(), |acc, x| M::op(&acc, &x))
+    }
+
+    /// `v`の部分木の値を位置の順に集約する。
+    pub fn subtree_query(&self, v: usize) -> M::Item {
+        self.forward.query(self.hld.subtree_range(v))
+    }
+}
+
+/// パスや部分木への作用ができる、頂点に値を持つ木の上のセグメント木
+///
+/// `HldSegTree`と同様に、非可換なモノイドでもパスの集約は`u`から`v`への順になる。
+pub struct HldLazySegTree<M: Monoid, F: Act<M>> {
+    hld: Hld,
+    forward: LazySegTree<M, F>,
+    backward: LazySegTree<Rev<M>, F>,
+}
+
+impl<M: Monoid, F: Act<M>> fmt::Debug for HldLazySegTree<M, F>
+where
+    M::Item: fmt::Debug,
+    F::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HldLazySegTree").field("hld", &self.hld).field("forward", &self.forward).finish()
+    }
+}
+
+impl<M: Monoid, F: Act<M>> Clone for HldLazySegTree<M, F>
+where
+    M::Item: Clone,
+    F::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            hld: self.hld.clone(),
+            forward: self.forward.clone(),
+            backward: self.backward.clone(),
+        }
+    }
+}
+
+impl<M: Monoid, F: Act<M>> HldLazySegTree<M, F>
+where
+    M::Item: Clone,
+{
+    /// `values[v]`を頂点`v`の値とする。
+    pub fn new(adj: &[Vec<usize>], root: usize, values: &[M::Item]) -> Self {
+        let hld = Hld::new(adj, root);
+        let leaves = hld.arrange(values);
+        Self {
+            forward: LazySegTree::from_slice(&leaves),
+            backward: LazySegTree::from_slice(&leaves),
+            hld,
+        }
+    }
+}
+
+impl<M: Monoid, F: Act<M>> HldLazySegTree<M, F> {
+    pub fn hld(&self) -> &Hld {
+        &self.hld
+    }
+
+    pub fn get(&self, v: usize) -> M::Item {
+        self.forward.get(self.hld.pos(v))
+    }
+
+    /// `u`から`v`へのパス上の頂点の値を、`u`の側から順に集約する。
+    pub fn path_query(&self, u: usize, v: usize) -> M::Item {
+        let (up, down) = self.hld.path_ranges(u, v);
+        let up = up.into_iter().map(|r| self.backward.query(r));
+        let down = down.into_iter().map(|r| self.forward.query(r));
+        up.chain(down).fold(M::identity(), |acc, x| M::op(&acc, &x))
+    }
+
+    pub fn subtree_query(&self, v: usize) -> M::Item {
+        self.forward.query(self.hld.subtree_range(v))
+    }
+
+    fn apply(&mut self, range: Range<usize>, f: &F::Item)
+    where
+        F::Item: Clone,
+    {
+        self.forward.apply(range.clone(), f.clone());
+        self.backward.apply(range, f.clone());
+    }
+
+    /// `u`から`v`へのパス上の各頂点に`f`を作用させる。
+    pub fn path_update(&mut self, u: usize, v: usize, f: F::Item)
+    where
+        F::Item: Clone,
+    {
+        let (up, down) = self.hld.path_ranges(u, v);
+        for range in up.into_iter().chain(down) {
+            self.apply(range, &f);
+        }
+    }
+
+    /// `v`の部分木の各頂点に`f`を作用させる。
+    pub fn subtree_update(&mut self, v: usize, f: F::Item)
+    where
+        F::Item: Clone,
+    {
+        self.apply(self.hld.subtree_range(v), &f);
+    }
+}
+
+#[test]
+fn hld_test() {
+    use crate::monoid::{Add, Affine, Sum};
+
+    let mut rand = crate::test_util::xorshift(0x853c49e6748fea9b);
+
+    for n in [1, 2, 3, 10, 40] {
+        let mut parent = vec![0; n];
+        let mut adj = vec![Vec::new(); n];
+        for v in 1..n {
+            parent[v] = rand(v);
+            adj[v].push(parent[v]);
+            adj[parent[v]].push(v);
+        }
+        let path = |mut u: usize, mut v: usize| {
+            let (mut up, mut down) = (Vec::new(), Vec::new());
+            while u != v {
+                if u > v {
+                    up.push(u);
+                    u = parent[u];
+                } else {
+                    down.push(v);
+                    v = parent[v];
+                }
+            }
+            // 親の番号は子より小さいので、出会った頂点が最小共通祖先
+            let lca = u;
+            up.push(u);
+            up.extend(down.into_iter().rev());
+            (up, lca)
+        };
+        let in_subtree = |mut u: usize, v: usize| loop {
+            if u == v {
+                return true;
+            }
+            if u == 0 {
+                return false;
+            }
+            u = parent[u];
+        };
+
+        let mut values = (0..n).map(|i| (i as i64 % 3 - 1, i as i64)).collect::<Vec<_>>();
+        let mut tree = HldSegTree::<Affine<i64>>::new(&adj, 0, &values);
+        let mut sums = (0..n as i64).collect::<Vec<_>>();
+        let mut lazy = HldLazySegTree::<Sum<i64>, Add<i64>>::new(&adj, 0, &sums);
+        let naive = crate::test_util::affine_fold;
+        for _ in 0..200 {
+            let (u, v) = (rand(n), rand(n));
+            let (p, lca) = path(u, v);
+            assert_eq!(lca, tree.hld().lca(u, v));
+            assert_eq!(naive(&p.iter().map(|&w| values[w]).collect::<Vec<_>>()), tree.path_query(u, v));
+            assert_eq!(p.iter().map(|&w| sums[w]).sum::<i64>(), lazy.path_query(u, v));
+            assert_eq!((0..n).filter(|&w| in_subtree(w, u)).map(|w| sums[w]).sum::<i64>(), lazy.subtree_query(u));
+            assert_eq!(tree.hld().subtree_range(u).len(), (0..n).filter(|&w| in_subtree(w, u)).count());
+
+            let x = rand(10) as i64 - 5;
+            match rand(3) {
+                0 => {
+                    values[u] = (x, v as i64);
+                    tree.update(u, values[u]);
+                }
+                1 => {
+                    for &w in &p {
+                        sums[w] += x;
+                    }
+                    lazy.path_update(u, v, x);
+                }
+                _ => {
+                    for w in (0..n).filter(|&w| in_subtree(w, v)) {
+                        sums[w] += x;
+                    }
+                    lazy.subtree_update(v, x);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::{fmt, ops::RangeBounds};
+
+use crate::{
+    monoid::{Act, Monoid},
+    to_range, BinTreeVec, Indexer,
+};
+
+/// 遅延評価セグメント木
+///
+/// 値のモノイド`M`と、それに作用するモノイド`F`を取る。
+/// 区間への作用と区間の集約がどちらもO(log n)でできる。
+pub struct LazySegTree<M: Monoid, F: Act<M>> {
+    tree: BinTreeVec<M::Item>,
+    // 内部ノードにのみ持つ。子に対して未適用の作用。
+    lazy: BinTreeVec<F::Item>,
+    len: usize,
+}
+
+impl<M: Monoid, F: Act<M>> fmt::Debug for LazySegTree<M, F>
+where
+    M::Item: fmt::Debug,
+    F::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazySegTree")
+            .field("tree", &self.tree)
+            .field("lazy", &self.lazy)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<M: Monoid, F: Act<M>> Clone for LazySegTree<M, F>
+where
+    M::Item: Clone,
+    F::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            lazy: self.lazy.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<M: Monoid, F: Act<M>> LazySegTree<M, F>
+where
+    M::Item: Clone,
+{
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        let len = slc.len();
+        let len_aligned = len.next_power_of_two();
+        let cap = len_aligned * 2 - 1;
+        let mut v = Vec::with_capacity(cap);
+        v.resize_with(len_aligned - 1, M::identity);
+        v.extend(slc.iter().cloned());
+        v.resize_with(cap, M::identity);
+        for i in (0..(len_aligned - 1)).rev() {
+            let (ch1, ch2) = Indexer(i).child();
+            v[i] = M::op(&v[ch1.0], &v[ch2.0]);
+        }
+        let mut lazy = Vec::with_capacity(len_aligned - 1);
+        lazy.resize_with(len_aligned - 1, F::identity);
+
+        Self {
+            tree: BinTreeVec(v),
+            lazy: BinTreeVec(lazy),
+            len,
+        }
+    }
+}
+
+impl<M: Monoid, F: Act<M>> LazySegTree<M, F> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn len_aligned(&self) -> usize {
+        self.lazy.len() + 1
+    }
+
+    fn apply_node(&mut self, index: Indexer, f: &F::Item, len: usize) {
+        self.tree[index] = F::act(f, &self.tree[index], len);
+        if index.0 < self.lazy.len() {
+            self.lazy[index] = F::op(&self.lazy[index], f);
+        }
+    }
+
+    fn push(&mut self, index: Indexer, len: usize) {
+        let f = std::mem::replace(&mut self.lazy[index], F::identity());
+        let (ch1, ch2) = index.child();
+        self.apply_node(ch1, &f, len / 2);
+        self.apply_node(ch2, &f, len / 2);
+    }
+
+    fn apply_rec(&mut self, index: Indexer, node_l: usize, node_r: usize, l: usize, r: usize, f: &F::Item) {
+        if r <= node_l || node_r <= l {
+            return;
+        }
+        if l <= node_l && node_r <= r {
+            self.apply_node(index, f, node_r - node_l);
+            return;
+        }
+        self.push(index, node_r - node_l);
+        let mid = (node_l + node_r) / 2;
+        let (ch1, ch2) = index.child();
+        self.apply_rec(ch1, node_l, mid, l, r, f);
+        self.apply_rec(ch2, mid, node_r, l, r, f);
+        self.tree[index] = M::op(&self.tree[ch1], &self.tree[ch2]);
+    }
+
+    /// 区間`range`の各要素に`f`を作用させる。
+    pub fn apply<R: RangeBounds<usize>>(&mut self, range: R, f: F::Item) {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        if l == r {
+            return;
+        }
+        self.apply_rec(Indexer(0), 0, self.len_aligned(), l, r, &f);
+    }
+
+    // `acc`は祖先ノードに溜まっている作用を合成したもの
+    fn query_rec(&self, index: Indexer, node_l: usize, node_r: usize, l: usize, r: usize, acc: &F::Item) -> M::Item {
+        if r <= node_l || node_r <= l {
+            return M::identity();
+        }
+        if l <= node_l && node_r <= r {
+            return F::act(acc, &self.tree[index], node_r - node_l);
+        }
+        let acc = F::op(&self.lazy[index], acc);
+        let mid = (node_l + node_r) / 2;
+        let (ch1, ch2) = index.child();
+        M::op(
+            &self.query_rec(ch1, node_l, mid, l, r, &acc),
+            &self.query_rec(ch2, mid, node_r, l, r, &acc),
+        )
+    }
+
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> M::Item {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        if l == r {
+            return M::identity();
+        }
+        self.query_rec(Indexer(0), 0, self.len_aligned(), l, r, &F::identity())
+    }
+
+    pub fn get(&self, index: usize) -> M::Item {
+        assert!(index < self.len, "index {index} out of bounds for length {}", self.len);
+        let mut idx = Indexer(index + self.len_aligned() - 1);
+        let mut val = F::act(&F::identity(), &self.tree[idx], 1);
+        while !idx.is_root() {
+            idx = idx.parent();
+            val = F::act(&self.lazy[idx], &val, 1);
+        }
+        val
+    }
+}
+
+#[test]
+fn lazy_segtree_test() {
+    use crate::monoid::{Add, Affine, Assign, Min, Sum};
+
+    let mut rand = crate::test_util::xorshift(0x2545f4914f6cdd1d);
+
+    for n in [1, 2, 5, 8, 13] {
+        let naive: Vec<i64> = (0..n).map(|_| rand(20) as i64 - 10).collect();
+        let mut add = LazySegTree::<Sum<i64>, Add<i64>>::from_slice(&naive);
+        let mut assign = LazySegTree::<Min<i64>, Assign<i64>>::from_slice(&naive);
+        let mut affine = LazySegTree::<Sum<i64>, Affine<i64>>::from_slice(&naive);
+        let (mut naive_add, mut naive_assign, mut naive_affine) = (naive.clone(), naive.clone(), naive.clone());
+        for _ in 0..200 {
+            let l = rand(n + 1);
+            let r = l + rand(n + 1 - l);
+            let v = rand(20) as i64 - 10;
+            match rand(4) {
+                0 => {
+                    add.apply(l..r, v);
+                    naive_add[l..r].iter_mut().for_each(|x| *x += v);
+                }
+                1 => {
+                    assign.apply(l..r, Some(v));
+                    naive_assign[l..r].iter_mut().for_each(|x| *x = v);
+                }
+                2 => {
+                    let a = if v < 0 { -1 } else { 1 };
+                    affine.apply(l..r, (a, v));
+                    naive_affine[l..r].iter_mut().for_each(|x| *x = a * *x + v);
+                }
+                _ => {
+                    assert_eq!(naive_add[l..r].iter().sum::<i64>(), add.query(l..r));
+                    assert_eq!(naive_assign[l..r].iter().copied().min().unwrap_or(i64::MAX), assign.query(l..r));
+                    assert_eq!(naive_affine[l..r].iter().sum::<i64>(), affine.query(l..r));
+                }
+            }
+        }
+        for i in 0..n {
+            assert_eq!(naive_add[i], add.get(i));
+            assert_eq!(naive_assign[i], assign.get(i));
+            assert_eq!(naive_affine[i], affine.get(i));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    mem,
+    ops::{Deref, DerefMut, Index, IndexMut, Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
+    slice, vec,
+};
+
+use crate::{monoid::Monoid, to_range, Indexer, SegTree};
+
+/// `SegTree::leaves_mut`が返すガード
+///
+/// 葉を自由に書き換えられ、ドロップ時に書き換えた葉の祖先だけをまとめて再計算する。
+/// `guard[i]`や`guard[l..r]`で書き換えた葉だけが記録され、
+/// それ以外の方法でスライスとして可変借用した場合や、書き換えた葉が多い場合はすべての葉が再計算の対象になる。
+pub struct LeavesMut<'a, M: Monoid> {
+    segtree: &'a mut SegTree<M>,
+    dirty: Vec<Indexer>,
+    all_dirty: bool,
+}
+
+impl<M: Monoid> SegTree<M> {
+    pub fn iter(&self) -> slice::Iter<'_, M::Item> {
+        self.deref().iter()
+    }
+
+    /// 複数の葉をまとめて書き換えるためのガードを返す。
+    pub fn leaves_mut(&mut self) -> LeavesMut<'_, M> {
+        LeavesMut {
+            segtree: self,
+            dirty: Vec::new(),
+            all_dirty: false,
+        }
+    }
+}
+
+impl<M: Monoid> Deref for LeavesMut<'_, M> {
+    type Target = [M::Item];
+
+    fn deref(&self) -> &Self::Target {
+        self.segtree
+    }
+}
+
+impl<M: Monoid> DerefMut for LeavesMut<'_, M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.all_dirty = true;
+        self.dirty = Vec::new();
+        let offset = self.segtree.get_indexer(0).0;
+        &mut self.segtree.tree.0[offset..(offset + self.segtree.len)]
+    }
+}
+
+impl<M: Monoid> LeavesMut<'_, M> {
+    // 書き換える葉が全体の1/8を超えたら、記録するより全体を再計算するほうが速い
+    fn mark_dirty(&mut self, l: usize, r: usize) {
+        assert!(l <= r && r <= self.segtree.len, "range {l}..{r} out of bounds for length {}", self.segtree.len);
+        if self.all_dirty {
+            return;
+        }
+        if (self.dirty.len() + (r - l)) * 8 > self.segtree.len {
+            self.all_dirty = true;
+            self.dirty = Vec::new();
+        } else {
+            self.dirty.extend((l..r).map(|i| self.segtree.get_indexer(i)));
+        }
+    }
+}
+
+impl<M: Monoid> Index<usize> for LeavesMut<'_, M> {
+    type Output = M::Item;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.deref()[index]
+    }
+}
+
+impl<M: Monoid> IndexMut<usize> for LeavesMut<'_, M> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.mark_dirty(index, index.saturating_add(1));
+        let idx = self.segtree.get_indexer(index);
+        &mut self.segtree.tree[idx]
+    }
+}
+
+macro_rules! impl_index_range {
+    ($($range:ty),*) => {
+        $(
+            impl<M: Monoid> Index<$range> for LeavesMut<'_, M> {
+                type Output = [M::Item];
+
+                fn index(&self, index: $range) -> &Self::Output {
+                    &self.deref()[index]
+                }
+            }
+
+            impl<M: Monoid> IndexMut<$range> for LeavesMut<'_, M> {
+                /// 範囲内の葉だけを再計算の対象にする。
+                fn index_mut(&mut self, index: $range) -> &mut Self::Output {
+                    let (l, r) = to_range(&index, self.segtree.len);
+                    self.mark_dirty(l, r);
+                    let offset = self.segtree.get_indexer(0).0;
+                    &mut self.segtree.tree.0[(offset + l)..(offset + r)]
+                }
+            }
+        )*
+    };
+}
+
+impl_index_range!(
+    Range<usize>,
+    RangeFrom<usize>,
+    RangeTo<usize>,
+    RangeInclusive<usize>,
+    RangeToInclusive<usize>,
+    RangeFull
+);
+
+impl<M: Monoid> Drop for LeavesMut<'_, M> {
+    fn drop(&mut self) {
+        if self.all_dirty {
+            for i in (0..(self.segtree.len_aligned() - 1)).rev() {
+                self.segtree.evaluate(Indexer(i));
+            }
+        } else {
+            self.segtree.evaluate_ancestors(mem::take(&mut self.dirty));
+        }
+    }
+}
+
+impl<'a, M: Monoid> IntoIterator for &'a SegTree<M> {
+    type Item = &'a M::Item;
+    type IntoIter = slice::Iter<'a, M::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<M: Monoid> IntoIterator for SegTree<M> {
+    type Item = M::Item;
+    type IntoIter = vec::IntoIter<M::Item>;
+
+    /// 葉を先頭から順に返す。
+    fn into_iter(self) -> Self::IntoIter {
+        let offset = self.get_indexer(0).0;
+        let mut v = self.tree.0;
+        v.truncate(offset + self.len);
+        v.drain(..offset);
+        v.into_iter()
+    }
+}
+
+#[test]
+fn leaves_mut_test() {
+    use crate::monoid::Affine;
+
+    let naive = crate::test_util::affine_fold;
+    let mut v = (0..13).map(|i| (i % 3 - 1, i)).collect::<Vec<_>>();
+    let mut segtree = SegTree::<Affine<i64>>::from_slice(&v);
+
+    {
+        let mut leaves = segtree.leaves_mut();
+        leaves[3] = (2, 5);
+        leaves[12].1 -= 7;
+        leaves[3].0 = 1;
+        assert_eq!((1, 5), leaves[3]);
+    }
+    v[3] = (1, 5);
+    v[12].1 -= 7;
+    assert_eq!(v, segtree.iter().copied().collect::<Vec<_>>());
+    assert_eq!(naive(&v), segtree.query(..));
+
+    segtree.leaves_mut()[2..9].reverse();
+    v[2..9].reverse();
+    segtree.leaves_mut().iter_mut().for_each(|f| f.1 += 1);
+    v.iter_mut().for_each(|f| f.1 += 1);
+    for l in 0..v.len() {
+        for r in l..=v.len() {
+            assert_eq!(naive(&v[l..r]), segtree.query(l..r));
+        }
+    }
+
+    for (a, b) in (&segtree).into_iter().zip(&v) {
+        assert_eq!(a, b);
+    }
+    assert_eq!(v, segtree.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn leaves_mut_full_test() {
+    use crate::monoid::Sum;
+
+    let mut v = (0..100).collect::<Vec<i64>>();
+    let mut segtree = SegTree::<Sum<i64>>::from_slice(&v);
+    {
+        let mut leaves = segtree.leaves_mut();
+        leaves[..].reverse();
+        assert!(leaves.all_dirty && leaves.dirty.capacity() == 0);
+        leaves[5] = 1000;
+    }
+    v.reverse();
+    v[5] = 1000;
+    {
+        let mut leaves = segtree.leaves_mut();
+        leaves[..10].fill(-1);
+        assert!(!leaves.all_dirty && leaves.dirty.len() == 10);
+        leaves[10..20].fill(-2);
+        assert!(leaves.all_dirty);
+    }
+    v[..10].fill(-1);
+    v[10..20].fill(-2);
+    for l in 0..v.len() {
+        assert_eq!(v[l..].iter().sum::<i64>(), segtree.query(l..));
+        assert_eq!(v[..l].iter().sum::<i64>(), segtree.query(..l));
+    }
+}
@@ -0,0 +1,255 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{BinTreeVec, Indexer};
+
+/// 直線`y = a * x + b`を`(a, b)`で表したもの
+pub type Line = (i64, i64);
+
+// `i64`同士の積と和は`i128`に必ず収まるので、比較はオーバーフローせずに正確に行える
+fn eval(line: &Line, x: i64) -> i128 {
+    line.0 as i128 * x as i128 + line.1 as i128
+}
+
+#[derive(Debug, Clone)]
+enum Domain {
+    /// `lo..lo + len`の整数すべて
+    Range { lo: i64, len: usize },
+    /// ソート済みで重複のない座標
+    Compressed(Vec<i64>),
+}
+
+impl Domain {
+    fn len(&self) -> usize {
+        match self {
+            Self::Range { len, .. } => *len,
+            Self::Compressed(xs) => xs.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> i64 {
+        match self {
+            Self::Range { lo, .. } => lo + i as i64,
+            Self::Compressed(xs) => xs[i],
+        }
+    }
+
+    /// `pred`を満たさない最初の添字 (`pred`は単調)
+    fn partition_point(&self, pred: impl Fn(i64) -> bool) -> usize {
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if pred(self.get(mid)) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    fn position(&self, x: i64) -> Option<usize> {
+        let i = self.partition_point(|y| y < x);
+        (i < self.len() && self.get(i) == x).then_some(i)
+    }
+}
+
+/// Li Chao Tree
+///
+/// 構築時に与えた`x`座標の集合上で、直線・線分の追加と最小値の取得ができる。
+/// 直線の追加と取得はO(log n)、線分の追加はO(log^2 n)。
+/// 最大値が欲しい場合は傾きと切片の符号を反転して使う。
+///
+/// 直線の比較は`i128`で行うので、`a * x + b`が`i64`に収まらない直線を追加してもよい。
+/// ただし`query`の結果の最小値は`i64`に収まる必要がある。
+#[derive(Debug, Clone)]
+pub struct LiChaoTree {
+    domain: Domain,
+    // 各ノードの区間の中央で最小となる直線
+    tree: BinTreeVec<Option<Line>>,
+}
+
+impl LiChaoTree {
+    /// `xs`に含まれる座標で取得できる木を作る。`xs`はソートされていなくてもよい。
+    pub fn new(xs: &[i64]) -> Self {
+        let mut xs = xs.to_vec();
+        xs.sort_unstable();
+        xs.dedup();
+        Self::with_domain(Domain::Compressed(xs))
+    }
+
+    /// `lo..=hi`のすべての整数座標で取得できる木を作る。座標の列は保持しない。
+    ///
+    /// 幅`hi - lo + 1`を2冪に切り上げた数の2倍のノード (1つあたり24バイト) を確保するので、
+    /// 座標が広い範囲にまばらにある場合は`new`で圧縮したほうがよい。
+    ///
+    /// # Panics
+    /// `lo > hi`の場合や、ノード数が`usize`に収まらない場合
+    pub fn with_range(lo: i64, hi: i64) -> Self {
+        assert!(lo <= hi, "empty range {lo}..={hi}");
+        let len = hi.abs_diff(lo).checked_add(1).and_then(|len| usize::try_from(len).ok()).expect("range is too large");
+        Self::with_domain(Domain::Range { lo, len })
+    }
+
+    fn with_domain(domain: Domain) -> Self {
+        let size = domain.len().checked_next_power_of_two().and_then(|n| n.checked_mul(2)).expect("range is too large");
+        Self {
+            domain,
+            tree: BinTreeVec(vec![None; size - 1]),
+        }
+    }
+
+    fn len_aligned(&self) -> usize {
+        self.tree.len() / 2 + 1
+    }
+
+    // パディング部分は最後の座標と同じとみなす
+    fn x(&self, i: usize) -> i64 {
+        self.domain.get(i.min(self.domain.len() - 1))
+    }
+
+    fn add_line_rec(&mut self, mut index: Indexer, mut node_l: usize, mut node_r: usize, mut line: Line) {
+        loop {
+            let mid = (node_l + node_r) / 2;
+            let (xl, xm, xr) = (self.x(node_l), self.x(mid), self.x(node_r - 1));
+            let Some(cur) = self.tree[index].as_mut() else {
+                self.tree[index] = Some(line);
+                return;
+            };
+            let left_better = eval(&line, xl) < eval(cur, xl);
+            let mid_better = eval(&line, xm) < eval(cur, xm);
+            let right_better = eval(&line, xr) < eval(cur, xr);
+            if mid_better {
+                std::mem::swap(cur, &mut line);
+            }
+            if node_r - node_l == 1 || left_better == right_better {
+                return;
+            }
+            let (ch1, ch2) = index.child();
+            if left_better != mid_better {
+                (index, node_r) = (ch1, mid);
+            } else {
+                (index, node_l) = (ch2, mid);
+            }
+        }
+    }
+
+    /// 全域に直線を追加する。
+    pub fn add_line(&mut self, line: Line) {
+        if self.domain.len() == 0 {
+            return;
+        }
+        self.add_line_rec(Indexer(0), 0, self.len_aligned(), line);
+    }
+
+    fn add_segment_rec(&mut self, index: Indexer, node_l: usize, node_r: usize, l: usize, r: usize, line: Line) {
+        if r <= node_l || node_r <= l {
+            return;
+        }
+        if l <= node_l && node_r <= r {
+            self.add_line_rec(index, node_l, node_r, line);
+            return;
+        }
+        let mid = (node_l + node_r) / 2;
+        let (ch1, ch2) = index.child();
+        self.add_segment_rec(ch1, node_l, mid, l, r, line);
+        self.add_segment_rec(ch2, mid, node_r, l, r, line);
+    }
+
+    /// `x`座標が`range`に含まれる部分にだけ直線を追加する。
+    pub fn add_segment<R: RangeBounds<i64>>(&mut self, range: R, line: Line) {
+        let l = match range.start_bound() {
+            Bound::Excluded(&l) => self.domain.partition_point(|x| x <= l),
+            Bound::Included(&l) => self.domain.partition_point(|x| x < l),
+            Bound::Unbounded => 0,
+        };
+        let r = match range.end_bound() {
+            Bound::Excluded(&r) => self.domain.partition_point(|x| x < r),
+            Bound::Included(&r) => self.domain.partition_point(|x| x <= r),
+            Bound::Unbounded => self.domain.len(),
+        };
+        if l < r {
+            self.add_segment_rec(Indexer(0), 0, self.len_aligned(), l, r, line);
+        }
+    }
+
+    /// `x`での最小値。`x`を通る直線がなければ`None`を返す。
+    ///
+    /// # Panics
+    /// `x`が構築時に与えた座標に含まれない場合や、最小値が`i64`に収まらない場合
+    pub fn query(&self, x: i64) -> Option<i64> {
+        let i = self.domain.position(x).expect("x is not in the domain");
+        let mut idx = Indexer(i + self.len_aligned() - 1);
+        let mut result = self.tree[idx].map(|line| eval(&line, x));
+        while !idx.is_root() {
+            idx = idx.parent();
+            if let Some(line) = &self.tree[idx] {
+                let y = eval(line, x);
+                result = Some(result.map_or(y, |r| r.min(y)));
+            }
+        }
+        result.map(|y| i64::try_from(y).expect("the minimum does not fit in i64"))
+    }
+}
+
+#[test]
+fn li_chao_test() {
+    let xs = [-7, -3, 0, 1, 2, 5, 8, 13, 21];
+    let mut tree = LiChaoTree::new(&xs);
+    let mut lines: Vec<(Line, i64, i64)> = Vec::new();
+    let ops = [
+        ((2, 3), i64::MIN, i64::MAX),
+        ((-1, 10), i64::MIN, i64::MAX),
+        ((0, 4), -3, 5),
+        ((-3, -20), 8, 13),
+        ((1, -5), 0, 0),
+        ((5, 0), i64::MIN, -3),
+        ((-2, 1), 2, 21),
+    ];
+    for (line, lo, hi) in ops {
+        if lo == i64::MIN && hi == i64::MAX {
+            tree.add_line(line);
+        } else {
+            tree.add_segment(lo..=hi, line);
+        }
+        lines.push((line, lo, hi));
+        for &x in &xs {
+            let expected = lines
+                .iter()
+                .filter(|&&(_, lo, hi)| lo <= x && x <= hi)
+                .map(|(line, _, _)| line.0 * x + line.1)
+                .min();
+            assert_eq!(expected, tree.query(x));
+        }
+    }
+
+    let mut full = LiChaoTree::with_range(0, 100);
+    assert_eq!(None, full.query(50));
+    for k in 0..10 {
+        full.add_line((-2 * k, k * k));
+    }
+    full.add_segment(40..60, (0, -1000));
+    for x in 0..=100 {
+        let expected = (0..10).map(|k| -2 * k * x + k * k).min().unwrap();
+        let expected = if (40..60).contains(&x) { expected.min(-1000) } else { expected };
+        assert_eq!(Some(expected), full.query(x));
+    }
+}
+
+#[test]
+fn li_chao_overflow_test() {
+    // 比較の途中で`i64`に収まらない値が出ても、最小値が収まれば正しく求まる
+    let xs = [-1_000_000_000_000, 0, 1_000_000_000_000];
+    let mut tree = LiChaoTree::new(&xs);
+    tree.add_line((1_000_000_000, 0));
+    tree.add_line((-1, 5));
+    assert_eq!(Some(0), tree.query(0));
+    assert_eq!(Some(-999_999_999_995), tree.query(1_000_000_000_000));
+    let result = std::panic::catch_unwind(|| tree.query(-1_000_000_000_000));
+    assert!(result.is_err());
+}
+
+#[test]
+#[should_panic(expected = "range is too large")]
+fn li_chao_full_range_test() {
+    LiChaoTree::with_range(i64::MIN, i64::MAX);
+}
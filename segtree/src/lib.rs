@@ -1,20 +1,75 @@
-use std::{mem::{ManuallyDrop, MaybeUninit}, ops::{self, Bound, Deref, Index, IndexMut, RangeBounds}};
+use std::ops::{self, Bound, Deref, Index, IndexMut, RangeBounds};
+
+pub mod beats;
+pub mod compact;
+pub mod dynamic;
+pub mod error;
+pub mod fenwick;
+pub mod hld;
+pub mod lazy;
+mod leaves;
+pub mod li_chao;
+pub mod merge_sort_tree;
+pub mod monoid;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod persistent;
+mod snapshot;
+pub mod sparse_table;
+#[cfg(test)]
+mod test_util;
+pub mod treap;
+pub mod two_dim;
+pub mod wavelet;
+
+pub use beats::SegTreeBeats;
+pub use compact::CompactSegTree;
+pub use dynamic::DynamicSegTree;
+pub use error::{OverflowError, SegTreeError};
+pub use fenwick::{Fenwick, RangeFenwick};
+pub use hld::{Hld, HldLazySegTree, HldSegTree};
+pub use lazy::LazySegTree;
+pub use leaves::LeavesMut;
+pub use li_chao::LiChaoTree;
+pub use merge_sort_tree::MergeSortTree;
+pub use monoid::{Act, Idempotent, Monoid};
+pub use persistent::PersistentSegTree;
+#[cfg(feature = "binary")]
+pub use snapshot::LeBytes;
+pub use sparse_table::SparseTable;
+pub use treap::ImplicitTreap;
+pub use two_dim::{CompressedSegTree2D, Fenwick2D, SegTree2D};
+pub use wavelet::WaveletMatrix;
+
+fn to_range<R: RangeBounds<usize>>(range: &R, len: usize) -> (usize, usize) {
+    let l = match range.start_bound() {
+        Bound::Excluded(&l) => l.saturating_add(1),
+        Bound::Included(&l) => l,
+        Bound::Unbounded => 0,
+    };
+    let r = match range.end_bound() {
+        Bound::Excluded(&r) => r,
+        Bound::Included(&r) => r.saturating_add(1),
+        Bound::Unbounded => len,
+    };
+    (l, r)
+}
 
 #[derive(Debug, Clone)]
-struct BinTreeVec(Vec<i32>);
+struct BinTreeVec<T>(Vec<T>);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Indexer(usize);
 
-impl Index<Indexer> for BinTreeVec {
-    type Output = i32;
+impl<T> Index<Indexer> for BinTreeVec<T> {
+    type Output = T;
 
     fn index(&self, index: Indexer) -> &Self::Output {
         &self.0[index.0]
     }
 }
 
-impl IndexMut<Indexer> for BinTreeVec {
+impl<T> IndexMut<Indexer> for BinTreeVec<T> {
     fn index_mut(&mut self, index: Indexer) -> &mut Self::Output {
         &mut self.0[index.0]
     }
@@ -56,7 +111,7 @@ impl Indexer {
     }
 
     const fn parent(self) -> Self {
-        Self((self.0 + 1) / 2 - 1)
+        Self(self.0.div_ceil(2) - 1)
     }
 
     const fn is_root(self) -> bool {
@@ -64,85 +119,143 @@ impl Indexer {
     }
 
     const fn is_left(self) -> bool {
-        self.0 % 2 == 1
+        !self.0.is_multiple_of(2)
     }
 
     const fn is_right(self) -> bool {
-        self.0 % 2 == 0
+        self.0.is_multiple_of(2)
     }
 }
 
-impl BinTreeVec {
+impl<T> BinTreeVec<T> {
     fn len(&self) -> usize {
         self.0.len()
     }
+}
+
+pub struct SegTree<M: Monoid> {
+    tree: BinTreeVec<M::Item>,
+    len: usize,
+}
 
-    fn is_empty(&self) -> bool {
-        self.0.is_empty()
+impl<M: Monoid> std::fmt::Debug for SegTree<M>
+where
+    M::Item: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegTree").field("tree", &self.tree).field("len", &self.len).finish()
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SegTree {
-    tree: BinTreeVec,
+impl<M: Monoid> Clone for SegTree<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            len: self.len,
+        }
+    }
 }
 
-impl SegTree {
-    pub fn from_slice(slc: &[i32]) -> Self {
-        let len = slc.len();
-        let len_aligned = len.next_power_of_two();
-        let cap = len_aligned.wrapping_mul(2).wrapping_sub(1);
-        let mut v = vec![MaybeUninit::uninit(); cap];
-        v[(cap - len_aligned)..].iter_mut().zip(slc.iter().copied().chain(std::iter::repeat(0))).for_each(|(d, s)| {
-            d.write(s);
-        });
-        for i in (0..(cap - len_aligned)).rev() {
-            let val = unsafe {
-                let (ch1, ch2) = Indexer(i).child();
-                v[ch1.0].assume_init_read() + v[ch2.0].assume_init_read()
-            };
-            v[i].write(val);
+impl<M: Monoid> SegTree<M>
+where
+    M::Item: Clone,
+{
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        Self::from(slc.to_vec())
+    }
+}
+
+impl<M: Monoid> From<Vec<M::Item>> for SegTree<M> {
+    /// `v`の領域をそのまま使って木を構築する。
+    fn from(v: Vec<M::Item>) -> Self {
+        let mut segtree = Self::with_leaves(v);
+        for i in (0..(segtree.len_aligned() - 1)).rev() {
+            segtree.evaluate(Indexer(i));
         }
-        let mut v = ManuallyDrop::new(v);
+        segtree
+    }
+}
 
-        Self {
-            tree: unsafe {
-                BinTreeVec(Vec::from_raw_parts(v.as_mut_ptr() as *mut i32, v.len(), v.capacity()))
-            },
+impl<M: Monoid> FromIterator<M::Item> for SegTree<M> {
+    fn from_iter<I: IntoIterator<Item = M::Item>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<M: Monoid> Extend<M::Item> for SegTree<M> {
+    /// 末尾に要素を追加する。新しい葉の祖先だけを再計算する。
+    fn extend<I: IntoIterator<Item = M::Item>>(&mut self, iter: I) {
+        let start = self.len;
+        for value in iter {
+            if self.len == self.len_aligned() {
+                self.grow();
+            }
+            let idx = self.get_indexer(self.len);
+            self.tree[idx] = value;
+            self.len += 1;
+        }
+        if start == self.len {
+            return;
+        }
+
+        let mut left = self.get_indexer(start);
+        let mut right = self.get_indexer(self.len - 1);
+        while !left.is_root() {
+            left = left.parent();
+            right = right.parent();
+            for i in left.0..=right.0 {
+                self.evaluate(Indexer(i));
+            }
         }
     }
+}
 
+impl<M: Monoid> SegTree<M> {
     pub fn is_empty(&self) -> bool {
-        self.tree.is_empty()
+        self.len == 0
     }
 
-    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> i32 {
-        if self.is_empty() {
-            return 0;
+    fn check_index(&self, index: usize) -> Result<(), SegTreeError> {
+        if index >= self.len {
+            Err(SegTreeError::OutOfBounds { index, len: self.len })
+        } else {
+            Ok(())
         }
-        let length = self.tree.len();
-        let offset = Indexer(length).parent();
-        let mut left = offset + match range.start_bound() {
-            Bound::Excluded(&l) => l + 1,
-            Bound::Included(&l) => l,
-            Bound::Unbounded => 0,
-        };
-        let mut right = match range.end_bound() {
-            Bound::Excluded(&r) => offset + r - 1,
-            Bound::Included(&r) => offset + r,
-            Bound::Unbounded => Indexer(length - 1),
-        };
+    }
 
-        let mut result1 = 0;
-        let mut result2 = 0;
+    fn check_range<R: RangeBounds<usize>>(&self, range: &R) -> Result<(usize, usize), SegTreeError> {
+        let (l, r) = to_range(range, self.len);
+        if r > self.len {
+            Err(SegTreeError::OutOfBounds { index: r, len: self.len })
+        } else if l > r {
+            Err(SegTreeError::InvertedRange { start: l, end: r })
+        } else if l == r {
+            Err(SegTreeError::EmptyRange)
+        } else {
+            Ok((l, r))
+        }
+    }
+
+    /// 区間`range`の集約値を返す。
+    ///
+    /// 区間が空の場合は`Err(SegTreeError::EmptyRange)`を返す。
+    pub fn try_query<R: RangeBounds<usize>>(&self, range: R) -> Result<M::Item, SegTreeError> {
+        let (l, r) = self.check_range(&range)?;
+        let mut left = self.get_indexer(l);
+        let mut right = self.get_indexer(r - 1);
+
+        let mut result1 = M::identity();
+        let mut result2 = M::identity();
         while left < right {
             if left.is_right() {
-                result1 += self.tree[left];
+                result1 = M::op(&result1, &self.tree[left]);
                 left += 1;
             }
             if right.is_left() {
-                // result2 = self.tree[right] + result2;
-                result2 += self.tree[right];
+                result2 = M::op(&self.tree[right], &result2);
                 right -= 1;
             }
             left = left.parent();
@@ -151,17 +264,29 @@ impl SegTree {
 
         if left > right {
             debug_assert_eq!(left, right + 1);
-            result1 + result2
+            Ok(M::op(&result1, &result2))
         } else {
             debug_assert_eq!(left, right);
-            result1 + self.tree[left] + result2
+            Ok(M::op(&M::op(&result1, &self.tree[left]), &result2))
+        }
+    }
+
+    /// 区間`range`の集約値を返す。区間が空の場合は`M::identity()`を返す。
+    ///
+    /// # Panics
+    /// 区間の終端が`self.len()`を超える場合や、始端が終端より大きい場合
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> M::Item {
+        match self.try_query(range) {
+            Ok(val) => val,
+            Err(SegTreeError::EmptyRange) => M::identity(),
+            Err(e) => panic!("{e}"),
         }
     }
 
     fn evaluate(&mut self, index: Indexer) {
         let val = {
             let (ch1, ch2) = index.child();
-            self.tree[ch1] + self.tree[ch2]
+            M::op(&self.tree[ch1], &self.tree[ch2])
         };
         self.tree[index] = val;
     }
@@ -170,28 +295,234 @@ impl SegTree {
         Indexer(index + self.tree.len() / 2)
     }
 
-    pub fn update(&mut self, index: usize, value: i32) {
+    fn len_aligned(&self) -> usize {
+        self.tree.len() / 2 + 1
+    }
+
+    // `v`を葉とし、内部ノードがすべて単位元の(未計算の)木を作る
+    fn with_leaves(mut v: Vec<M::Item>) -> Self {
+        let len = v.len();
+        let len_aligned = len.next_power_of_two();
+        v.reserve_exact(len_aligned * 2 - 1 - len);
+        v.resize_with(len_aligned, M::identity);
+        v.extend(std::iter::repeat_with(M::identity).take(len_aligned - 1));
+        // 葉を後ろに移動する
+        v.rotate_right(len_aligned - 1);
+        Self {
+            tree: BinTreeVec(v),
+            len,
+        }
+    }
+
+    // 葉の数を2倍にする。元の木は新しい根の左の部分木になる。
+    fn grow(&mut self) {
+        let len_aligned = self.len_aligned();
+        let v = &mut self.tree.0;
+        v.resize_with(len_aligned * 4 - 1, M::identity);
+        let mut width = len_aligned;
+        while width > 0 {
+            // 深さが同じノードは`width - 1..width * 2 - 1`にあり、移動後は`width * 2 - 1..width * 3 - 1`に来る
+            for i in (width - 1)..(width * 2 - 1) {
+                v.swap(i, i + width);
+            }
+            for d in &mut v[(width * 3 - 1)..(width * 4 - 1)] {
+                *d = M::identity();
+            }
+            width /= 2;
+        }
+        self.evaluate(Indexer(0));
+    }
+
+    /// 末尾に要素を追加する。
+    pub fn push(&mut self, value: M::Item) {
+        if self.len == self.len_aligned() {
+            self.grow();
+        }
+        self.len += 1;
+        self.update(self.len - 1, value);
+    }
+
+    /// `index`番目の値を`value`にする。`index`が範囲外の場合は何もせずにエラーを返す。
+    pub fn try_update(&mut self, index: usize, value: M::Item) -> Result<(), SegTreeError> {
+        self.check_index(index)?;
         let mut idx = self.get_indexer(index);
         self.tree[idx] = value;
         while !idx.is_root() {
             idx = idx.parent();
             self.evaluate(idx);
         }
+        Ok(())
+    }
+
+    /// 複数の一点更新をまとめて行う。同じ添字が複数回現れた場合は後のものが優先される。
+    ///
+    /// 更新された葉の祖先はそれぞれ一度だけ再計算される。
+    ///
+    /// # Panics
+    /// 添字が`self.len()`以上のものが含まれる場合。このとき木は変更されない。
+    pub fn update_batch(&mut self, updates: &[(usize, M::Item)])
+    where
+        M::Item: Clone,
+    {
+        if let Some(&(index, _)) = updates.iter().find(|(i, _)| *i >= self.len) {
+            panic!("{}", SegTreeError::OutOfBounds { index, len: self.len });
+        }
+        let mut dirty = Vec::with_capacity(updates.len());
+        for (index, value) in updates {
+            let idx = self.get_indexer(*index);
+            self.tree[idx] = value.clone();
+            dirty.push(idx);
+        }
+        self.evaluate_ancestors(dirty);
+    }
+
+    // `dirty`の葉の祖先をそれぞれ一度だけ再計算する
+    fn evaluate_ancestors(&mut self, mut dirty: Vec<Indexer>) {
+        dirty.sort_unstable();
+        dirty.dedup();
+        while dirty.first().is_some_and(|idx| !idx.is_root()) {
+            for idx in &mut dirty {
+                *idx = idx.parent();
+            }
+            dirty.dedup();
+            for &idx in &dirty {
+                self.evaluate(idx);
+            }
+        }
+    }
+
+    /// # Panics
+    /// `index`が`self.len()`以上の場合
+    pub fn update(&mut self, index: usize, value: M::Item) {
+        if let Err(e) = self.try_update(index, value) {
+            panic!("{e}");
+        }
+    }
+
+    /// `pred(self.query(l..r))`が`true`となる最大の`r`を返す。
+    ///
+    /// `pred`は単調である必要があり、`pred(M::identity())`は`true`でなければならない。
+    pub fn max_right(&self, l: usize, mut pred: impl FnMut(&M::Item) -> bool) -> usize {
+        assert!(l <= self.len);
+        debug_assert!(pred(&M::identity()));
+        if l == self.len {
+            return l;
+        }
+        let leaf_offset = self.get_indexer(0).0;
+        let mut idx = self.get_indexer(l);
+        let mut acc = M::identity();
+        loop {
+            while idx.is_left() {
+                idx = idx.parent();
+            }
+            let tmp = M::op(&acc, &self.tree[idx]);
+            if !pred(&tmp) {
+                while idx.0 < leaf_offset {
+                    idx = idx.child().0;
+                    let tmp = M::op(&acc, &self.tree[idx]);
+                    if pred(&tmp) {
+                        acc = tmp;
+                        idx += 1;
+                    }
+                }
+                return idx.0 - leaf_offset;
+            }
+            acc = tmp;
+            idx += 1;
+            // 右端まで到達した
+            if (idx.0 + 1).is_power_of_two() {
+                return self.len;
+            }
+        }
+    }
+
+    /// `pred(self.query(l..r))`が`true`となる最小の`l`を返す。
+    ///
+    /// `pred`は単調である必要があり、`pred(M::identity())`は`true`でなければならない。
+    pub fn min_left(&self, r: usize, mut pred: impl FnMut(&M::Item) -> bool) -> usize {
+        assert!(r <= self.len);
+        debug_assert!(pred(&M::identity()));
+        if r == 0 {
+            return 0;
+        }
+        let leaf_offset = self.get_indexer(0).0;
+        let mut idx = self.get_indexer(r);
+        let mut acc = M::identity();
+        loop {
+            idx -= 1;
+            while !idx.is_root() && idx.is_right() {
+                idx = idx.parent();
+            }
+            let tmp = M::op(&self.tree[idx], &acc);
+            if !pred(&tmp) {
+                while idx.0 < leaf_offset {
+                    idx = idx.child().1;
+                    let tmp = M::op(&self.tree[idx], &acc);
+                    if pred(&tmp) {
+                        acc = tmp;
+                        idx -= 1;
+                    }
+                }
+                return idx.0 + 1 - leaf_offset;
+            }
+            acc = tmp;
+            // 左端まで到達した
+            if (idx.0 + 1).is_power_of_two() {
+                return 0;
+            }
+        }
+    }
+}
+
+impl<T> SegTree<monoid::CheckedSum<T>>
+where
+    monoid::CheckedSum<T>: Monoid<Item = Option<T>>,
+{
+    /// `index`番目の値を`value`にする。
+    /// 更新後に全体の和がオーバーフローする場合は何もせずにエラーを返す。
+    ///
+    /// # Panics
+    /// `index`が`self.len()`以上の場合
+    pub fn checked_update(&mut self, index: usize, value: T) -> Result<(), OverflowError> {
+        if let Err(e) = self.check_index(index) {
+            panic!("{e}");
+        }
+        // 根までの新しい値を求めてから書き込む。`None`は上に伝わるので、見つけた時点で諦めてよい
+        let mut idx = self.get_indexer(index);
+        let mut path = vec![(idx, Some(value))];
+        while !idx.is_root() {
+            let acc = &path.last().unwrap().1;
+            let val = if idx.is_left() {
+                monoid::CheckedSum::<T>::op(acc, &self.tree[idx + 1])
+            } else {
+                monoid::CheckedSum::<T>::op(&self.tree[idx - 1], acc)
+            };
+            if val.is_none() {
+                return Err(OverflowError);
+            }
+            idx = idx.parent();
+            path.push((idx, val));
+        }
+        for (idx, val) in path {
+            self.tree[idx] = val;
+        }
+        Ok(())
     }
 }
 
-impl Deref for SegTree {
-    type Target = [i32];
+impl<M: Monoid> Deref for SegTree<M> {
+    type Target = [M::Item];
 
     fn deref(&self) -> &Self::Target {
-        &self.tree.0[self.get_indexer(0).0..]
+        let offset = self.get_indexer(0).0;
+        &self.tree.0[offset..(offset + self.len)]
     }
 }
 
 #[test]
 fn from_slice_test() {
     let s = [2, 4, 5, 6, 2];
-    let mut segtree = SegTree::from_slice(&s);
+    let mut segtree = SegTree::<monoid::Sum<i32>>::from_slice(&s);
     println!("{segtree:?}");
     assert_eq!(11, segtree.query(2..4));
     assert_eq!(9, segtree.query(1..3));
@@ -202,3 +533,173 @@ fn from_slice_test() {
     println!("{segtree:?}");
     println!("{:?}", &segtree[..]);
 }
+
+#[test]
+fn non_commutative_test() {
+    // x -> 2x + 1, x -> x + 3, x -> 3x, x -> x - 1
+    let s = [(2, 1), (1, 3), (3, 0), (1, -1)];
+    let mut segtree = SegTree::<monoid::Affine<i64>>::from_slice(&s);
+    let naive = |l: usize, r: usize, s: &[(i64, i64)]| test_util::affine_fold(&s[l..r]);
+    for l in 0..s.len() {
+        for r in (l + 1)..=s.len() {
+            assert_eq!(naive(l, r, &s), segtree.query(l..r));
+        }
+    }
+
+    segtree.update(1, (5, 2));
+    let s = [(2, 1), (5, 2), (3, 0), (1, -1)];
+    assert_eq!(naive(0, 4, &s), segtree.query(..));
+    assert_eq!(naive(1, 3, &s), segtree.query(1..=2));
+
+    let mins = SegTree::<monoid::Min<i32>>::from_slice(&[5, 3, 8, 1, 9]);
+    assert_eq!(3, mins.query(..3));
+    assert_eq!(1, mins.query(2..));
+}
+
+#[test]
+fn binary_search_test() {
+    let s = [3, 1, 4, 1, 5, 9, 2, 6, 5];
+    let segtree = SegTree::<monoid::Sum<u32>>::from_slice(&s);
+    for l in 0..=s.len() {
+        for limit in 0..40 {
+            let expected = (l..=s.len()).rev().find(|&r| s[l..r].iter().sum::<u32>() <= limit).unwrap();
+            assert_eq!(expected, segtree.max_right(l, |&x| x <= limit));
+        }
+    }
+    for r in 0..=s.len() {
+        for limit in 0..40 {
+            let expected = (0..=r).find(|&l| s[l..r].iter().sum::<u32>() <= limit).unwrap();
+            assert_eq!(expected, segtree.min_left(r, |&x| x <= limit));
+        }
+    }
+}
+
+#[test]
+fn push_extend_test() {
+    let naive = crate::test_util::affine_fold;
+    let mut v = Vec::new();
+    let mut segtree = SegTree::<monoid::Affine<i64>>::from_iter(v.iter().copied());
+    for i in 0..20 {
+        let f = (i % 3 - 1, i);
+        v.push(f);
+        segtree.push(f);
+        assert_eq!(v.len(), segtree.len());
+        assert_eq!(naive(&v), segtree.query(..));
+    }
+    for n in [1, 3, 4, 17, 40] {
+        let new = (0..n).map(|i| (i % 2 + 1, -i)).collect::<Vec<_>>();
+        v.extend(new.iter().copied());
+        segtree.extend(new);
+        assert_eq!(&v[..], &segtree[..]);
+        for l in 0..v.len() {
+            assert_eq!(naive(&v[l..]), segtree.query(l..v.len()));
+        }
+    }
+    assert_eq!(v, SegTree::<monoid::Affine<i64>>::from(v.clone())[..]);
+}
+
+#[test]
+fn overflow_mode_test() {
+    use monoid::{CheckedSum, ModProduct, ModSum, SaturatingSum, WrappingSum};
+
+    let s = [200u8, 50, 10, 3];
+    let wrapping = SegTree::<WrappingSum<u8>>::from_slice(&s);
+    assert_eq!(250, wrapping.query(..2));
+    assert_eq!(7, wrapping.query(..));
+    let saturating = SegTree::<SaturatingSum<u8>>::from_slice(&s);
+    assert_eq!(255, saturating.query(..));
+    assert_eq!(63, saturating.query(1..));
+
+    let mut checked = s.iter().map(|&x| Some(x)).collect::<SegTree<CheckedSum<u8>>>();
+    assert_eq!(None, checked.query(..));
+    assert_eq!(Some(250), checked.query(..2));
+    assert_eq!(Some(63), checked.query(1..));
+    checked.update(0, Some(100));
+    assert_eq!(Some(163), checked.query(..));
+    assert_eq!(Err(OverflowError), checked.checked_update(1, 200));
+    assert_eq!(Some(163), checked.query(..));
+    assert_eq!(Ok(()), checked.checked_update(1, 142));
+    assert_eq!(Some(255), checked.query(..));
+    // 範囲外の添字で詰め物の葉を壊さない
+    checked.push(Some(0));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| checked.checked_update(6, 0)));
+    assert!(result.is_err());
+    // 木全体を作り直すと、壊れた詰め物の葉があれば根が`None`になる
+    checked.leaves_mut()[0] = Some(100);
+    assert_eq!(Some(255), checked.query(..));
+    assert_eq!(Ok(()), checked.checked_update(4, 0));
+    assert_eq!(Err(OverflowError), checked.checked_update(4, 1));
+    assert_eq!(Some(255), checked.query(..));
+
+    // 符号付き整数では途中の和がはみ出しても、全体の和が収まれば正しく求まる
+    let s = [i32::MAX, 1, -2, i32::MIN, -1, 3];
+    let checked = s.iter().map(|&x| x.into()).collect::<SegTree<CheckedSum<i32>>>();
+    let saturating = s.iter().map(|&x| x.into()).collect::<SegTree<SaturatingSum<i32>>>();
+    for l in 0..s.len() {
+        for r in l + 1..=s.len() {
+            let exact = s[l..r].iter().map(|&x| x as i64).sum::<i64>();
+            assert_eq!(i32::try_from(exact).ok(), checked.query(l..r).checked());
+            assert_eq!(exact.clamp(i32::MIN as i64, i32::MAX as i64) as i32, saturating.query(l..r).saturating());
+            assert_eq!(exact as i128, checked.query(l..r).get());
+        }
+    }
+    assert_eq!(Some(i32::MAX - 1), checked.query(..3).checked());
+    assert_eq!(None, checked.query(..2).checked());
+    assert_eq!(i32::MIN, saturating.query(3..5).saturating());
+
+    const P: u64 = 998_244_353;
+    let s = [P - 1, P - 2, 123_456_789, 1 << 40];
+    let sum = SegTree::<ModSum<P>>::from_slice(&s.map(|x| x % P));
+    let prod = SegTree::<ModProduct<P>>::from_slice(&s.map(|x| x % P));
+    let naive_sum = s.iter().fold(0u128, |acc, &x| (acc + x as u128) % P as u128) as u64;
+    let naive_prod = s.iter().fold(1u128, |acc, &x| acc * (x % P) as u128 % P as u128) as u64;
+    assert_eq!(naive_sum, sum.query(..));
+    assert_eq!(naive_prod, prod.query(..));
+    assert_eq!(2, prod.query(..2));
+}
+
+#[test]
+fn bounds_test() {
+    let mut segtree = SegTree::<monoid::Sum<i32>>::from_slice(&[1, 2, 3, 4, 5]);
+    assert_eq!(Ok(15), segtree.try_query(..));
+    assert_eq!(Ok(5), segtree.try_query(4..=4));
+    assert_eq!(Err(SegTreeError::EmptyRange), segtree.try_query(..0));
+    assert_eq!(Err(SegTreeError::EmptyRange), segtree.try_query(5..));
+    #[allow(clippy::reversed_empty_ranges)]
+    let inverted = segtree.try_query(5..3);
+    assert_eq!(Err(SegTreeError::InvertedRange { start: 5, end: 3 }), inverted);
+    assert_eq!(Err(SegTreeError::OutOfBounds { index: 6, len: 5 }), segtree.try_query(2..6));
+    assert_eq!(Err(SegTreeError::OutOfBounds { index: usize::MAX, len: 5 }), segtree.try_query(..=usize::MAX));
+    assert_eq!(0, segtree.query(..0));
+    assert_eq!(0, segtree.query(3..3));
+
+    assert_eq!(Err(SegTreeError::OutOfBounds { index: 5, len: 5 }), segtree.try_update(5, 100));
+    assert_eq!(15, segtree.query(..));
+    assert_eq!(Ok(()), segtree.try_update(4, 100));
+    assert_eq!(110, segtree.query(..));
+
+    let empty = SegTree::<monoid::Sum<i32>>::from_slice(&[]);
+    assert!(empty.is_empty());
+    assert_eq!(0, empty.query(..));
+    assert_eq!(Err(SegTreeError::EmptyRange), empty.try_query(..));
+}
+
+#[test]
+fn update_batch_test() {
+    let naive = crate::test_util::affine_fold;
+    let mut v = (0..13).map(|i| (i % 3 - 1, i)).collect::<Vec<_>>();
+    let mut segtree = SegTree::<monoid::Affine<i64>>::from_slice(&v);
+    let updates = [(3, (2, 5)), (12, (1, -7)), (0, (-1, 1)), (3, (1, 4)), (7, (2, 2))];
+    segtree.update_batch(&updates);
+    for &(i, f) in &updates {
+        v[i] = f;
+    }
+    assert_eq!(&v[..], &segtree[..]);
+    for l in 0..v.len() {
+        for r in l..=v.len() {
+            assert_eq!(naive(&v[l..r]), segtree.query(l..r));
+        }
+    }
+    segtree.update_batch(&[]);
+    assert_eq!(naive(&v), segtree.query(..));
+}
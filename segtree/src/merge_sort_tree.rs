@@ -0,0 +1,133 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{to_range, BinTreeVec, Indexer};
+
+/// Merge Sort Tree
+///
+/// 各ノードに区間内の値をソートしたものを持つ。メモリはO(n log n)。
+/// 区間内の値の個数の取得はO(log^2 n)、k番目に小さい値の取得はO(log^3 n)。
+#[derive(Debug, Clone)]
+pub struct MergeSortTree<T> {
+    tree: BinTreeVec<Vec<T>>,
+    len: usize,
+}
+
+impl<T: Ord + Clone> MergeSortTree<T> {
+    pub fn from_slice(slc: &[T]) -> Self {
+        let len = slc.len();
+        let len_aligned = len.next_power_of_two();
+        let mut tree = BinTreeVec(vec![Vec::new(); len_aligned * 2 - 1]);
+        for (i, x) in slc.iter().enumerate() {
+            tree[Indexer(len_aligned - 1 + i)].push(x.clone());
+        }
+        for i in (0..(len_aligned - 1)).rev() {
+            let (ch1, ch2) = Indexer(i).child();
+            let (a, b) = (&tree[ch1], &tree[ch2]);
+            let mut merged = Vec::with_capacity(a.len() + b.len());
+            let (mut ia, mut ib) = (0, 0);
+            while ia < a.len() && ib < b.len() {
+                if b[ib] < a[ia] {
+                    merged.push(b[ib].clone());
+                    ib += 1;
+                } else {
+                    merged.push(a[ia].clone());
+                    ia += 1;
+                }
+            }
+            merged.extend_from_slice(&a[ia..]);
+            merged.extend_from_slice(&b[ib..]);
+            tree[Indexer(i)] = merged;
+        }
+        Self { tree, len }
+    }
+}
+
+impl<T: Ord> MergeSortTree<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // 区間を覆うノードそれぞれについて`f`を呼ぶ
+    fn for_each_node(&self, l: usize, r: usize, mut f: impl FnMut(&[T])) {
+        let offset = self.tree.len() / 2;
+        let mut left = Indexer(offset + l);
+        let mut right = Indexer(offset + r);
+        while left < right {
+            if left.is_right() {
+                f(&self.tree[left]);
+                left += 1;
+            }
+            if right.is_right() {
+                right -= 1;
+                f(&self.tree[right]);
+            }
+            left = left.parent();
+            right = right.parent();
+        }
+    }
+
+    fn count_by(&self, l: usize, r: usize, pred: impl Fn(&T) -> bool) -> usize {
+        let mut result = 0;
+        self.for_each_node(l, r, |v| result += v.partition_point(&pred));
+        result
+    }
+
+    /// 区間内で、値が`value_range`に含まれる要素の個数
+    pub fn range_freq<R: RangeBounds<usize>, V: RangeBounds<T>>(&self, range: R, value_range: V) -> usize {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        let upper = match value_range.end_bound() {
+            Bound::Excluded(x) => self.count_by(l, r, |v| v < x),
+            Bound::Included(x) => self.count_by(l, r, |v| v <= x),
+            Bound::Unbounded => r - l,
+        };
+        let lower = match value_range.start_bound() {
+            Bound::Excluded(x) => self.count_by(l, r, |v| v <= x),
+            Bound::Included(x) => self.count_by(l, r, |v| v < x),
+            Bound::Unbounded => 0,
+        };
+        upper.saturating_sub(lower)
+    }
+
+    /// 区間内で`k`番目 (0-indexed) に小さい値
+    pub fn quantile<R: RangeBounds<usize>>(&self, range: R, k: usize) -> &T {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        assert!(k < r - l, "k = {k} is out of range for {} elements", r - l);
+        // 根には全要素がソートされて入っているので、その中で二分探索する
+        let all = &self.tree[Indexer(0)];
+        let (mut lo, mut hi) = (0, all.len() - 1);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.count_by(l, r, |v| v <= &all[mid]) > k {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        &all[lo]
+    }
+}
+
+#[test]
+fn merge_sort_tree_test() {
+    let s = [5, -4, 5, 5, 2, 1, 5, 6, 1, 3, 5, 0, 9];
+    let tree = MergeSortTree::from_slice(&s);
+    for l in 0..=s.len() {
+        for r in l..=s.len() {
+            let mut sorted = s[l..r].to_vec();
+            sorted.sort();
+            for (k, v) in sorted.iter().enumerate() {
+                assert_eq!(v, tree.quantile(l..r, k));
+            }
+            for v in -5..11 {
+                assert_eq!(s[l..r].iter().filter(|&&x| x < v).count(), tree.range_freq(l..r, ..v));
+                assert_eq!(s[l..r].iter().filter(|&&x| (v..=v + 2).contains(&x)).count(), tree.range_freq(l..r, v..=v + 2));
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use std::marker::PhantomData;
+
+/// 単位元と結合的な二項演算を持つ代数構造。
+///
+/// `op`は結合法則を満たす必要があるが、可換である必要はない。
+pub trait Monoid {
+    type Item;
+
+    fn identity() -> Self::Item;
+
+    fn op(a: &Self::Item, b: &Self::Item) -> Self::Item;
+}
+
+/// 和
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sum<T>(PhantomData<T>);
+
+/// 最小値
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Min<T>(PhantomData<T>);
+
+/// 最大値
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Max<T>(PhantomData<T>);
+
+/// 排他的論理和
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xor<T>(PhantomData<T>);
+
+/// 最大公約数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gcd<T>(PhantomData<T>);
+
+/// オーバーフローしたときに折り返す和
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WrappingSum<T>(PhantomData<T>);
+
+/// オーバーフローしたときに最大値で止まる和
+///
+/// 符号付き整数では途中の和で止めると結合法則を満たさないので、要素を`ExactSum`にして正確な和を持ち、
+/// 最後に`ExactSum::saturating`で丸める。`i128`には実装していない。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaturatingSum<T>(PhantomData<T>);
+
+/// オーバーフローしたときに`None`になる和
+///
+/// 符号付き整数では`SaturatingSum`と同様に要素を`ExactSum`にして、最後に`ExactSum::checked`で確かめる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckedSum<T>(PhantomData<T>);
+
+/// 符号付き整数`T`の和を`i128`で正確に持つ値
+///
+/// 要素数は`isize::MAX`を超えないので、`i64`の和でも`i128`には収まる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExactSum<T> {
+    sum: i128,
+    _marker: PhantomData<T>,
+}
+
+impl<T> ExactSum<T> {
+    pub fn get(self) -> i128 {
+        self.sum
+    }
+}
+
+/// `P`を法とする和
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModSum<const P: u64>;
+
+/// `P`を法とする積
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModProduct<const P: u64>;
+
+/// 論理積
+#[derive(Debug, Clone, Copy, Default)]
+pub struct And<T>(PhantomData<T>);
+
+/// 論理和
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Or<T>(PhantomData<T>);
+
+/// 一次関数`x -> a * x + b`を`(a, b)`で表したときの合成。
+///
+/// `op(f, g)`は`f`を先に適用してから`g`を適用する関数になる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Affine<T>(PhantomData<T>);
+
+macro_rules! impl_int_monoids {
+    ($($t:ty),*) => {$(
+        impl Monoid for Sum<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a + b
+            }
+        }
+
+        impl Monoid for Min<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                <$t>::MAX
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                *a.min(b)
+            }
+        }
+
+        impl Monoid for Max<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                <$t>::MIN
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                *a.max(b)
+            }
+        }
+
+        impl Monoid for Xor<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a ^ b
+            }
+        }
+
+        impl Monoid for Affine<$t> {
+            type Item = ($t, $t);
+
+            fn identity() -> ($t, $t) {
+                (1, 0)
+            }
+
+            fn op(f: &($t, $t), g: &($t, $t)) -> ($t, $t) {
+                (g.0 * f.0, g.0 * f.1 + g.1)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_gcd {
+    ($($t:ty),*) => {$(
+        impl Monoid for Gcd<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                let (mut a, mut b) = (*a, *b);
+                while b != 0 {
+                    (a, b) = (b, a % b);
+                }
+                a
+            }
+        }
+
+        impl Idempotent for Gcd<$t> {}
+    )*};
+}
+
+macro_rules! impl_float_monoids {
+    ($($t:ty),*) => {$(
+        impl Monoid for Sum<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0.0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a + b
+            }
+        }
+
+        impl Monoid for Min<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                <$t>::INFINITY
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a.min(*b)
+            }
+        }
+
+        impl Monoid for Max<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                <$t>::NEG_INFINITY
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a.max(*b)
+            }
+        }
+
+        impl Idempotent for Min<$t> {}
+        impl Idempotent for Max<$t> {}
+    )*};
+}
+
+/// `op(a, a) == a`を満たすモノイド。
+pub trait Idempotent: Monoid {}
+
+macro_rules! impl_bit_monoids {
+    ($($t:ty),*) => {$(
+        impl Monoid for And<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                !0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a & b
+            }
+        }
+
+        impl Monoid for Or<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a | b
+            }
+        }
+
+        impl Idempotent for And<$t> {}
+        impl Idempotent for Or<$t> {}
+        impl Idempotent for Min<$t> {}
+        impl Idempotent for Max<$t> {}
+    )*};
+}
+
+macro_rules! impl_wrapping_sum {
+    ($($t:ty),*) => {$(
+        impl Monoid for WrappingSum<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a.wrapping_add(*b)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_unsigned_sums {
+    ($($t:ty),*) => {$(
+        impl Monoid for CheckedSum<$t> {
+            type Item = Option<$t>;
+
+            fn identity() -> Option<$t> {
+                Some(0)
+            }
+
+            fn op(a: &Option<$t>, b: &Option<$t>) -> Option<$t> {
+                a.zip(*b).and_then(|(a, b)| a.checked_add(b))
+            }
+        }
+
+        impl Monoid for SaturatingSum<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a.saturating_add(*b)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed_sums {
+    ($($t:ty),*) => {$(
+        impl From<$t> for ExactSum<$t> {
+            fn from(x: $t) -> Self {
+                Self {
+                    sum: x as i128,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl ExactSum<$t> {
+            /// `T`に収まらなければ`None`を返す。
+            pub fn checked(self) -> Option<$t> {
+                <$t>::try_from(self.sum).ok()
+            }
+
+            /// `T`の範囲に丸める。
+            pub fn saturating(self) -> $t {
+                self.sum.clamp(<$t>::MIN as i128, <$t>::MAX as i128) as $t
+            }
+        }
+
+        impl Monoid for CheckedSum<$t> {
+            type Item = ExactSum<$t>;
+
+            fn identity() -> ExactSum<$t> {
+                ExactSum::default()
+            }
+
+            fn op(a: &ExactSum<$t>, b: &ExactSum<$t>) -> ExactSum<$t> {
+                ExactSum {
+                    sum: a.sum + b.sum,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl Monoid for SaturatingSum<$t> {
+            type Item = ExactSum<$t>;
+
+            fn identity() -> ExactSum<$t> {
+                ExactSum::default()
+            }
+
+            fn op(a: &ExactSum<$t>, b: &ExactSum<$t>) -> ExactSum<$t> {
+                CheckedSum::<$t>::op(a, b)
+            }
+        }
+    )*};
+}
+
+impl<const P: u64> Monoid for ModSum<P> {
+    type Item = u64;
+
+    fn identity() -> u64 {
+        0
+    }
+
+    fn op(a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % P as u128) as u64
+    }
+}
+
+impl<const P: u64> Monoid for ModProduct<P> {
+    type Item = u64;
+
+    fn identity() -> u64 {
+        1 % P
+    }
+
+    fn op(a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % P as u128) as u64
+    }
+}
+
+impl_int_monoids!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_bit_monoids!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_wrapping_sum!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_unsigned_sums!(u8, u16, u32, u64, u128, usize);
+impl_signed_sums!(i8, i16, i32, i64, isize);
+impl_gcd!(u8, u16, u32, u64, u128, usize);
+impl_float_monoids!(f32, f64);
+
+/// 演算の順序を逆にしたモノイド
+///
+/// `Rev<M>`での`op(a, b)`は`M::op(b, a)`になる。区間を右から畳み込みたいときに使う。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rev<M>(PhantomData<M>);
+
+impl<M: Monoid> Monoid for Rev<M> {
+    type Item = M::Item;
+
+    fn identity() -> M::Item {
+        M::identity()
+    }
+
+    fn op(a: &M::Item, b: &M::Item) -> M::Item {
+        M::op(b, a)
+    }
+}
+
+/// 区間に対する作用。
+///
+/// `act(f, x, len)`は長さ`len`の区間の集約値`x`に`f`を作用させた値を返す。
+/// `Self::op(f, g)`は`f`を先に作用させてから`g`を作用させることを表す。
+pub trait Act<M: Monoid>: Monoid {
+    fn act(f: &Self::Item, x: &M::Item, len: usize) -> M::Item;
+}
+
+// `M`に正しく作用するなら、演算の順序を逆にしても同じ作用になる
+impl<M: Monoid, F: Act<M>> Act<Rev<M>> for F {
+    fn act(f: &F::Item, x: &M::Item, len: usize) -> M::Item {
+        <F as Act<M>>::act(f, x, len)
+    }
+}
+
+/// 区間加算
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Add<T>(PhantomData<T>);
+
+/// 区間代入
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Assign<T>(PhantomData<T>);
+
+impl<T: Clone> Monoid for Assign<T> {
+    type Item = Option<T>;
+
+    fn identity() -> Option<T> {
+        None
+    }
+
+    fn op(f: &Option<T>, g: &Option<T>) -> Option<T> {
+        g.as_ref().or(f.as_ref()).cloned()
+    }
+}
+
+macro_rules! impl_acts {
+    ($($t:ty),*) => {$(
+        impl Monoid for Add<$t> {
+            type Item = $t;
+
+            fn identity() -> $t {
+                0
+            }
+
+            fn op(a: &$t, b: &$t) -> $t {
+                a + b
+            }
+        }
+
+        impl Act<Sum<$t>> for Add<$t> {
+            fn act(f: &$t, x: &$t, len: usize) -> $t {
+                x + f * len as $t
+            }
+        }
+
+        impl Act<Min<$t>> for Add<$t> {
+            fn act(f: &$t, x: &$t, _len: usize) -> $t {
+                x + f
+            }
+        }
+
+        impl Act<Max<$t>> for Add<$t> {
+            fn act(f: &$t, x: &$t, _len: usize) -> $t {
+                x + f
+            }
+        }
+
+        impl Act<Sum<$t>> for Assign<$t> {
+            fn act(f: &Option<$t>, x: &$t, len: usize) -> $t {
+                f.map_or(*x, |v| v * len as $t)
+            }
+        }
+
+        impl Act<Min<$t>> for Assign<$t> {
+            fn act(f: &Option<$t>, x: &$t, _len: usize) -> $t {
+                f.unwrap_or(*x)
+            }
+        }
+
+        impl Act<Max<$t>> for Assign<$t> {
+            fn act(f: &Option<$t>, x: &$t, _len: usize) -> $t {
+                f.unwrap_or(*x)
+            }
+        }
+
+        impl Act<Sum<$t>> for Affine<$t> {
+            fn act(f: &($t, $t), x: &$t, len: usize) -> $t {
+                f.0 * x + f.1 * len as $t
+            }
+        }
+    )*};
+}
+
+impl_acts!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
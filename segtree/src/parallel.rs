@@ -0,0 +1,57 @@
+use rayon::prelude::*;
+
+use crate::{monoid::Monoid, SegTree};
+
+// これより幅の小さい段は並列化しない
+const PARALLEL_THRESHOLD: usize = 1 << 12;
+
+impl<M: Monoid> SegTree<M>
+where
+    M::Item: Send + Sync,
+{
+    /// `From<Vec<M::Item>>`と同じだが、内部ノードの計算を段ごとに並列に行う。
+    pub fn par_from_vec(v: Vec<M::Item>) -> Self {
+        let mut segtree = Self::with_leaves(v);
+        let mut width = segtree.len_aligned() / 2;
+        let v = &mut segtree.tree.0;
+        while width > 0 {
+            // 深さが同じノードは`width - 1..width * 2 - 1`にあり、その子は`width * 2 - 1..width * 4 - 1`にある
+            let (upper, lower) = v.split_at_mut(width * 2 - 1);
+            let level = &mut upper[(width - 1)..];
+            let lower = &*lower;
+            let eval = |(k, d): (usize, &mut M::Item)| *d = M::op(&lower[k * 2], &lower[k * 2 + 1]);
+            if width >= PARALLEL_THRESHOLD {
+                level.par_iter_mut().enumerate().for_each(eval);
+            } else {
+                level.iter_mut().enumerate().for_each(eval);
+            }
+            width /= 2;
+        }
+        segtree
+    }
+}
+
+impl<M: Monoid> SegTree<M>
+where
+    M::Item: Clone + Send + Sync,
+{
+    pub fn par_from_slice(slc: &[M::Item]) -> Self {
+        Self::par_from_vec(slc.to_vec())
+    }
+}
+
+#[test]
+fn par_from_vec_test() {
+    use crate::monoid::{Affine, Sum};
+
+    for n in [0, 1, 5, PARALLEL_THRESHOLD * 2, PARALLEL_THRESHOLD * 4 + 3] {
+        let v = (0..n as i64).map(|i| i * 7 % 13 - 6).collect::<Vec<_>>();
+        let seq = SegTree::<Sum<i64>>::from(v.clone());
+        let par = SegTree::<Sum<i64>>::par_from_vec(v);
+        assert_eq!(seq.tree.0, par.tree.0);
+    }
+    let v = (0..(PARALLEL_THRESHOLD as i64 * 3)).map(|i| (i % 3 - 1, i % 5)).collect::<Vec<_>>();
+    let seq = SegTree::<Affine<i64>>::from_slice(&v);
+    let par = SegTree::<Affine<i64>>::par_from_slice(&v);
+    assert_eq!(seq.tree.0, par.tree.0);
+}
@@ -0,0 +1,207 @@
+use std::{fmt, ops::RangeBounds};
+
+use crate::{monoid::Monoid, to_range};
+
+/// `PersistentSegTree`のバージョンを表すハンドル
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NodeId(usize);
+
+struct Node<T> {
+    value: T,
+    // 葉では使わない
+    child: (NodeId, NodeId),
+}
+
+/// 永続セグメント木
+///
+/// 一点更新のたびに根から葉までのパスだけを複製し、新しいバージョンを作る。
+/// ノードはすべて一つの`Vec`に確保する。
+pub struct PersistentSegTree<M: Monoid> {
+    nodes: Vec<Node<M::Item>>,
+    roots: Vec<NodeId>,
+    len: usize,
+}
+
+impl<M: Monoid> fmt::Debug for PersistentSegTree<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistentSegTree")
+            .field("nodes", &self.nodes.iter().map(|n| &n.value).collect::<Vec<_>>())
+            .field("roots", &self.roots)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<M: Monoid> PersistentSegTree<M>
+where
+    M::Item: Clone,
+{
+    /// `slc`を初期状態とする木を作る。初期状態のバージョンは`Self::initial()`で得られる。
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::with_capacity(slc.len().max(1) * 2 - 1),
+            roots: Vec::new(),
+            len: slc.len(),
+        };
+        let root = if slc.is_empty() {
+            tree.push(M::identity(), (NodeId(0), NodeId(0)))
+        } else {
+            tree.build(slc)
+        };
+        tree.roots.push(root);
+        tree
+    }
+
+    fn build(&mut self, slc: &[M::Item]) -> NodeId {
+        if slc.len() == 1 {
+            return self.push(slc[0].clone(), (NodeId(0), NodeId(0)));
+        }
+        let mid = slc.len() / 2;
+        let ch1 = self.build(&slc[..mid]);
+        let ch2 = self.build(&slc[mid..]);
+        self.push_parent(ch1, ch2)
+    }
+
+    fn update_rec(&mut self, node: NodeId, node_l: usize, node_r: usize, index: usize, value: M::Item) -> NodeId {
+        if node_r - node_l == 1 {
+            return self.push(value, (NodeId(0), NodeId(0)));
+        }
+        let mid = (node_l + node_r) / 2;
+        let (mut ch1, mut ch2) = self.nodes[node.0].child;
+        if index < mid {
+            ch1 = self.update_rec(ch1, node_l, mid, index, value);
+        } else {
+            ch2 = self.update_rec(ch2, mid, node_r, index, value);
+        }
+        self.push_parent(ch1, ch2)
+    }
+
+    /// `version`の`index`番目を`value`にした新しいバージョンを作る。`version`自体は変更されない。
+    pub fn update(&mut self, version: Version, index: usize, value: M::Item) -> Version {
+        assert!(index < self.len, "index {index} out of bounds for length {}", self.len);
+        let root = self.update_rec(self.roots[version.0], 0, self.len, index, value);
+        self.roots.push(root);
+        Version(self.roots.len() - 1)
+    }
+
+    fn query_rec(&self, node: NodeId, node_l: usize, node_r: usize, l: usize, r: usize) -> M::Item {
+        if r <= node_l || node_r <= l {
+            return M::identity();
+        }
+        let node = &self.nodes[node.0];
+        if l <= node_l && node_r <= r {
+            return node.value.clone();
+        }
+        let mid = (node_l + node_r) / 2;
+        M::op(
+            &self.query_rec(node.child.0, node_l, mid, l, r),
+            &self.query_rec(node.child.1, mid, node_r, l, r),
+        )
+    }
+
+    pub fn query<R: RangeBounds<usize>>(&self, version: Version, range: R) -> M::Item {
+        let (l, r) = to_range(&range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        if l == r {
+            return M::identity();
+        }
+        self.query_rec(self.roots[version.0], 0, self.len, l, r)
+    }
+}
+
+impl<M: Monoid> PersistentSegTree<M> {
+    fn push(&mut self, value: M::Item, child: (NodeId, NodeId)) -> NodeId {
+        self.nodes.push(Node { value, child });
+        NodeId(self.nodes.len() - 1)
+    }
+
+    fn push_parent(&mut self, ch1: NodeId, ch2: NodeId) -> NodeId {
+        let value = M::op(&self.nodes[ch1.0].value, &self.nodes[ch2.0].value);
+        self.push(value, (ch1, ch2))
+    }
+
+    pub const fn initial() -> Version {
+        Version(0)
+    }
+
+    /// 最新のバージョン
+    pub fn latest(&self) -> Version {
+        Version(self.roots.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, version: Version, index: usize) -> &M::Item {
+        assert!(index < self.len, "index {index} out of bounds for length {}", self.len);
+        let (mut node, mut node_l, mut node_r) = (self.roots[version.0], 0, self.len);
+        while node_r - node_l > 1 {
+            let mid = (node_l + node_r) / 2;
+            let child = self.nodes[node.0].child;
+            if index < mid {
+                (node, node_r) = (child.0, mid);
+            } else {
+                (node, node_l) = (child.1, mid);
+            }
+        }
+        &self.nodes[node.0].value
+    }
+}
+
+#[test]
+fn persistent_test() {
+    use crate::monoid::Sum;
+
+    let mut tree = PersistentSegTree::<Sum<i32>>::from_slice(&[1, 2, 3, 4, 5]);
+    let v0 = PersistentSegTree::<Sum<i32>>::initial();
+    let v1 = tree.update(v0, 2, 10);
+    let v2 = tree.update(v1, 0, -1);
+    let v3 = tree.update(v0, 4, 0);
+    assert_eq!(v3, tree.latest());
+
+    assert_eq!(15, tree.query(v0, ..));
+    assert_eq!(22, tree.query(v1, ..));
+    assert_eq!(20, tree.query(v2, ..));
+    assert_eq!(10, tree.query(v3, ..));
+    assert_eq!(5, tree.query(v0, 1..3));
+    assert_eq!(12, tree.query(v1, 1..3));
+    assert_eq!(&3, tree.get(v0, 2));
+    assert_eq!(&10, tree.get(v2, 2));
+    assert_eq!(&-1, tree.get(v2, 0));
+    assert_eq!(&1, tree.get(v1, 0));
+
+    // k番目に小さい値: 値の出現回数を持つ木を接頭辞ごとに永続化する
+    let a = [3usize, 1, 4, 1, 5, 2, 6, 5];
+    let mut count = PersistentSegTree::<Sum<usize>>::from_slice(&[0; 7]);
+    let mut versions = vec![PersistentSegTree::<Sum<usize>>::initial()];
+    for &x in &a {
+        let prev = *versions.last().unwrap();
+        let c = *count.get(prev, x);
+        versions.push(count.update(prev, x, c + 1));
+    }
+    let kth = |l: usize, r: usize, k: usize| {
+        (0..7)
+            .find(|&v| count.query(versions[r], ..=v) - count.query(versions[l], ..=v) > k)
+            .unwrap()
+    };
+    for l in 0..a.len() {
+        for r in (l + 1)..=a.len() {
+            let mut sorted = a[l..r].to_vec();
+            sorted.sort();
+            for (k, &v) in sorted.iter().enumerate() {
+                assert_eq!(v, kth(l, r, k));
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/// 保存形式のバージョン。形式を変えたら上げる。
+#[cfg(any(feature = "serde", feature = "binary"))]
+const FORMAT_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::FORMAT_VERSION;
+    use crate::{monoid::Monoid, SegTree};
+
+    // 葉だけを保存し、読み込み時に内部ノードを再計算する
+    #[derive(Serialize)]
+    struct ReprRef<'a, T> {
+        version: u32,
+        leaves: &'a [T],
+    }
+
+    #[derive(Deserialize)]
+    struct Repr<T> {
+        version: u32,
+        leaves: Vec<T>,
+    }
+
+    impl<M: Monoid> Serialize for SegTree<M>
+    where
+        M::Item: Serialize,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ReprRef {
+                version: FORMAT_VERSION,
+                leaves: &self[..],
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, M: Monoid> Deserialize<'de> for SegTree<M>
+    where
+        M::Item: Deserialize<'de>,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = Repr::deserialize(deserializer)?;
+            if repr.version != FORMAT_VERSION {
+                return Err(D::Error::custom(format_args!(
+                    "unsupported format version {} (expected {FORMAT_VERSION})",
+                    repr.version
+                )));
+            }
+            Ok(Self::from(repr.leaves))
+        }
+    }
+
+    #[test]
+    fn serde_test() {
+        use crate::monoid::Affine;
+
+        let segtree = SegTree::<Affine<i64>>::from_slice(&[(2, 1), (1, 3), (3, 0), (1, -1), (5, 5)]);
+        let json = serde_json::to_string(&segtree).unwrap();
+        assert_eq!(r#"{"version":1,"leaves":[[2,1],[1,3],[3,0],[1,-1],[5,5]]}"#, json);
+        let loaded: SegTree<Affine<i64>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(&segtree[..], &loaded[..]);
+        for l in 0..segtree.len() {
+            assert_eq!(segtree.query(l..), loaded.query(l..));
+        }
+        assert!(serde_json::from_str::<SegTree<Affine<i64>>>(r#"{"version":2,"leaves":[]}"#).is_err());
+    }
+}
+
+#[cfg(feature = "binary")]
+mod binary {
+    use std::io::{self, Read, Write};
+
+    use super::FORMAT_VERSION;
+    use crate::{monoid::Monoid, BinTreeVec, SegTree};
+
+    const MAGIC: [u8; 4] = *b"SEGT";
+    const HEADER_SIZE: usize = 24;
+
+    /// 固定長のリトルエンディアンのバイト列に変換できる型
+    pub trait LeBytes: Sized {
+        const SIZE: usize;
+
+        fn write_le(&self, buf: &mut [u8]);
+
+        fn read_le(buf: &[u8]) -> Self;
+    }
+
+    macro_rules! impl_le_bytes {
+        ($($t:ty),*) => {$(
+            impl LeBytes for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn write_le(&self, buf: &mut [u8]) {
+                    buf.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(buf: &[u8]) -> Self {
+                    <$t>::from_le_bytes(buf.try_into().unwrap())
+                }
+            }
+        )*};
+    }
+
+    impl_le_bytes!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64);
+
+    impl<A: LeBytes, B: LeBytes> LeBytes for (A, B) {
+        const SIZE: usize = A::SIZE + B::SIZE;
+
+        fn write_le(&self, buf: &mut [u8]) {
+            self.0.write_le(&mut buf[..A::SIZE]);
+            self.1.write_le(&mut buf[A::SIZE..]);
+        }
+
+        fn read_le(buf: &[u8]) -> Self {
+            (A::read_le(&buf[..A::SIZE]), B::read_le(&buf[A::SIZE..]))
+        }
+    }
+
+    fn invalid_data(msg: impl Into<String>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg.into())
+    }
+
+    impl<M: Monoid> SegTree<M>
+    where
+        M::Item: LeBytes,
+    {
+        /// 木を次の形式で書き出す。数値はすべてリトルエンディアン。
+        ///
+        /// | offset | size | 内容 |
+        /// |---|---|---|
+        /// | 0 | 4 | `b"SEGT"` |
+        /// | 4 | 4 | 形式のバージョン (u32) |
+        /// | 8 | 4 | 1ノードのバイト数 (u32) |
+        /// | 12 | 4 | 予約 (0) |
+        /// | 16 | 8 | 要素数 (u64) |
+        /// | 24 | | 内部ノードも含むすべてのノードを添字順に並べたもの |
+        ///
+        /// ノードは内部の配置と同じ順に並ぶので、`load`は内部ノードを再計算せずに読み込める。
+        /// 各ノードは`LeBytes::SIZE`バイトで詰めて並び、境界は揃えていないので、
+        /// ファイルをメモリマップして`M::Item`のスライスとして参照することはできない。
+        pub fn dump<W: Write>(&self, mut writer: W) -> io::Result<()> {
+            let mut header = [0u8; HEADER_SIZE];
+            header[0..4].copy_from_slice(&MAGIC);
+            header[4..8].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+            header[8..12].copy_from_slice(&(M::Item::SIZE as u32).to_le_bytes());
+            header[16..24].copy_from_slice(&(self.len as u64).to_le_bytes());
+            writer.write_all(&header)?;
+
+            let mut buf = vec![0u8; M::Item::SIZE];
+            for node in &self.tree.0 {
+                node.write_le(&mut buf);
+                writer.write_all(&buf)?;
+            }
+            Ok(())
+        }
+
+        /// `dump`で書き出した木を読み込む。
+        ///
+        /// 内部ノードの値は検証しないので、信頼できる入力にのみ使うこと。
+        pub fn load<R: Read>(mut reader: R) -> io::Result<Self> {
+            let mut header = [0u8; HEADER_SIZE];
+            reader.read_exact(&mut header)?;
+            if header[0..4] != MAGIC {
+                return Err(invalid_data("not a segment tree dump"));
+            }
+            let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+            if version != FORMAT_VERSION {
+                return Err(invalid_data(format!(
+                    "unsupported format version {version} (expected {FORMAT_VERSION})"
+                )));
+            }
+            let size = u32::from_le_bytes(header[8..12].try_into().unwrap());
+            if size as usize != M::Item::SIZE {
+                return Err(invalid_data(format!(
+                    "node size mismatch: {size} bytes in file, {} bytes expected",
+                    M::Item::SIZE
+                )));
+            }
+            let len = usize::try_from(u64::from_le_bytes(header[16..24].try_into().unwrap()))
+                .map_err(|_| invalid_data("length too large"))?;
+            let node_count = len
+                .checked_next_power_of_two()
+                .and_then(|n| n.checked_mul(2))
+                .ok_or_else(|| invalid_data("length too large"))?
+                - 1;
+
+            let mut tree = Vec::new();
+            let mut buf = vec![0u8; M::Item::SIZE];
+            for _ in 0..node_count {
+                reader.read_exact(&mut buf)?;
+                tree.push(M::Item::read_le(&buf));
+            }
+            Ok(Self {
+                tree: BinTreeVec(tree),
+                len,
+            })
+        }
+    }
+
+    #[test]
+    fn binary_test() {
+        use crate::monoid::{Affine, Sum};
+
+        let segtree = SegTree::<Sum<u32>>::from_slice(&[3, 1, 4, 1, 5]);
+        let mut bytes = Vec::new();
+        segtree.dump(&mut bytes).unwrap();
+        assert_eq!(HEADER_SIZE + 15 * 4, bytes.len());
+        assert_eq!(b"SEGT", &bytes[..4]);
+        assert_eq!(5, bytes[16]);
+        let loaded = SegTree::<Sum<u32>>::load(bytes.as_slice()).unwrap();
+        assert_eq!(&segtree[..], &loaded[..]);
+        assert_eq!(14, loaded.query(..));
+
+        assert!(SegTree::<Sum<u64>>::load(bytes.as_slice()).is_err());
+        assert!(SegTree::<Sum<u32>>::load(&bytes[..bytes.len() - 1]).is_err());
+        bytes[0] = b'X';
+        assert!(SegTree::<Sum<u32>>::load(bytes.as_slice()).is_err());
+
+        let affine = SegTree::<Affine<i32>>::from_slice(&[(2, 1), (1, 3), (3, 0)]);
+        let mut bytes = Vec::new();
+        affine.dump(&mut bytes).unwrap();
+        let loaded = SegTree::<Affine<i32>>::load(bytes.as_slice()).unwrap();
+        assert_eq!(affine.query(..), loaded.query(..));
+    }
+}
+
+#[cfg(feature = "binary")]
+pub use binary::LeBytes;
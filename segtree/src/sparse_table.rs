@@ -0,0 +1,97 @@
+use std::{
+    fmt,
+    ops::{Deref, RangeBounds},
+};
+
+use crate::{monoid::Idempotent, to_range};
+
+/// Sparse Table
+///
+/// 冪等なモノイドについて、構築O(n log n)、区間の集約O(1)で計算できる。
+/// 構築後に値を変更することはできない。
+pub struct SparseTable<M: Idempotent> {
+    // `table[k][i]`は`i..(i + 2^k)`の集約値
+    table: Vec<Vec<M::Item>>,
+}
+
+impl<M: Idempotent> fmt::Debug for SparseTable<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SparseTable").field("table", &self.table).finish()
+    }
+}
+
+impl<M: Idempotent> Clone for SparseTable<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+        }
+    }
+}
+
+impl<M: Idempotent> SparseTable<M>
+where
+    M::Item: Clone,
+{
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        let mut table = vec![slc.to_vec()];
+        let mut width = 1;
+        while width * 2 <= slc.len() {
+            let prev = table.last().unwrap();
+            let next = (0..=(slc.len() - width * 2))
+                .map(|i| M::op(&prev[i], &prev[i + width]))
+                .collect();
+            table.push(next);
+            width *= 2;
+        }
+        Self { table }
+    }
+}
+
+impl<M: Idempotent> SparseTable<M> {
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> M::Item {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r && r <= self.len(), "range {l}..{r} out of bounds for length {}", self.len());
+        if l == r {
+            return M::identity();
+        }
+        let k = (r - l).ilog2() as usize;
+        let row = &self.table[k];
+        M::op(&row[l], &row[r - (1 << k)])
+    }
+}
+
+impl<M: Idempotent> Deref for SparseTable<M> {
+    type Target = [M::Item];
+
+    fn deref(&self) -> &Self::Target {
+        &self.table[0]
+    }
+}
+
+#[test]
+fn sparse_table_test() {
+    use crate::monoid::{Gcd, Max, Min, Monoid};
+
+    let s = [12u32, 18, 6, 9, 27, 3, 14, 8, 30, 5, 10];
+    let min = SparseTable::<Min<u32>>::from_slice(&s);
+    let max = SparseTable::<Max<u32>>::from_slice(&s);
+    let gcd = SparseTable::<Gcd<u32>>::from_slice(&s);
+    for l in 0..=s.len() {
+        for r in l..=s.len() {
+            assert_eq!(s[l..r].iter().copied().min().unwrap_or(u32::MAX), min.query(l..r));
+            assert_eq!(s[l..r].iter().copied().max().unwrap_or(0), max.query(l..r));
+            assert_eq!(s[l..r].iter().fold(0, |a, b| Gcd::<u32>::op(&a, b)), gcd.query(l..r));
+        }
+    }
+    assert_eq!(3, min.query(..));
+    assert_eq!(27, max.query(2..=4));
+
+    let empty = SparseTable::<Min<i32>>::from_slice(&[]);
+    assert_eq!(i32::MAX, empty.query(..));
+}
@@ -0,0 +1,16 @@
+//! テストとベンチマークで共有する補助関数
+
+/// `0..n`から一様に近い値を返す xorshift 乱数
+pub fn xorshift(mut seed: u64) -> impl FnMut(usize) -> usize {
+    move |n| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % n as u64) as usize
+    }
+}
+
+/// `monoid::Affine<i64>`の列を左から順に合成する素朴な実装
+pub fn affine_fold(s: &[(i64, i64)]) -> (i64, i64) {
+    s.iter().fold((1, 0), |f, g| (g.0 * f.0, g.0 * f.1 + g.1))
+}
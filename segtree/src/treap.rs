@@ -0,0 +1,436 @@
+use std::{cmp::Ordering, fmt, mem, ops::RangeBounds};
+
+use crate::{monoid::Monoid, to_range};
+
+type Link<T> = Option<Box<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    // 部分木を左から畳み込んだ値
+    sum: T,
+    // 部分木を右から畳み込んだ値 (反転したときに使う)
+    rev_sum: T,
+    len: usize,
+    priority: u64,
+    // 子の部分木に未適用の反転
+    rev: bool,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+impl<T: Clone> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            sum: self.sum.clone(),
+            rev_sum: self.rev_sum.clone(),
+            len: self.len,
+            priority: self.priority,
+            rev: self.rev,
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<T> Node<T> {
+    // 自身の子の順序と畳み込みの値はすぐに入れ替え、孫以下は遅延させる
+    fn toggle(&mut self) {
+        mem::swap(&mut self.left, &mut self.right);
+        mem::swap(&mut self.sum, &mut self.rev_sum);
+        self.rev ^= true;
+    }
+
+    fn push(&mut self) {
+        if self.rev {
+            for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+                child.toggle();
+            }
+            self.rev = false;
+        }
+    }
+
+    /// 反転が`rev`だけ遅延しているときの、論理的な順序での左右の子
+    fn children(&self, rev: bool) -> (&Link<T>, &Link<T>) {
+        if rev {
+            (&self.right, &self.left)
+        } else {
+            (&self.left, &self.right)
+        }
+    }
+}
+
+fn len<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.len)
+}
+
+/// 暗黙のキーを持つTreap
+///
+/// 任意の位置への挿入・削除、区間の反転、分割・連結と、区間の畳み込みがいずれも期待O(log n)でできる。
+/// 非可換なモノイドでも反転後の畳み込みが正しくなるように、各ノードに逆順の畳み込みも持つ。
+pub struct ImplicitTreap<M: Monoid> {
+    root: Link<M::Item>,
+    seed: u64,
+}
+
+impl<M: Monoid> fmt::Debug for ImplicitTreap<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<M: Monoid> Clone for ImplicitTreap<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            seed: self.seed,
+        }
+    }
+}
+
+impl<M: Monoid> Default for ImplicitTreap<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Monoid> FromIterator<M::Item> for ImplicitTreap<M> {
+    fn from_iter<I: IntoIterator<Item = M::Item>>(iter: I) -> Self {
+        let mut treap = Self::new();
+        for value in iter {
+            treap.push(value);
+        }
+        treap
+    }
+}
+
+impl<M: Monoid> From<Vec<M::Item>> for ImplicitTreap<M> {
+    fn from(v: Vec<M::Item>) -> Self {
+        v.into_iter().collect()
+    }
+}
+
+impl<M: Monoid> ImplicitTreap<M>
+where
+    M::Item: Clone,
+{
+    pub fn from_slice(slc: &[M::Item]) -> Self {
+        slc.iter().cloned().collect()
+    }
+
+    fn query_rec(link: &Link<M::Item>, l: usize, r: usize, rev: bool) -> M::Item {
+        let Some(node) = link else {
+            return M::identity();
+        };
+        if l >= r {
+            return M::identity();
+        }
+        if l == 0 && r == node.len {
+            return if rev { node.rev_sum.clone() } else { node.sum.clone() };
+        }
+        let (left, right) = node.children(rev);
+        let left_len = len(left);
+        let child_rev = rev ^ node.rev;
+        let mut result = Self::query_rec(left, l, r.min(left_len), child_rev);
+        if l <= left_len && left_len < r {
+            result = M::op(&result, &node.value);
+        }
+        let (l, r) = (l.saturating_sub(left_len + 1), r.saturating_sub(left_len + 1));
+        M::op(&result, &Self::query_rec(right, l, r, child_rev))
+    }
+
+    pub fn query<R: RangeBounds<usize>>(&self, range: R) -> M::Item {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r && r <= self.len(), "range {l}..{r} out of bounds for length {}", self.len());
+        Self::query_rec(&self.root, l, r, false)
+    }
+}
+
+impl<M: Monoid> ImplicitTreap<M> {
+    pub const fn new() -> Self {
+        Self {
+            root: None,
+            seed: 0x9e3779b97f4a7c15,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        len(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    fn next_priority(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    fn new_node(&mut self, value: M::Item) -> Box<Node<M::Item>> {
+        let mut node = Box::new(Node {
+            value,
+            sum: M::identity(),
+            rev_sum: M::identity(),
+            len: 1,
+            priority: self.next_priority(),
+            rev: false,
+            left: None,
+            right: None,
+        });
+        Self::fix(&mut node);
+        node
+    }
+
+    // 子の値から`sum`、`rev_sum`、`len`を計算し直す
+    fn fix(node: &mut Node<M::Item>) {
+        let identity = M::identity();
+        let (left_sum, left_rev) = node.left.as_ref().map_or((&identity, &identity), |n| (&n.sum, &n.rev_sum));
+        let (right_sum, right_rev) = node.right.as_ref().map_or((&identity, &identity), |n| (&n.sum, &n.rev_sum));
+        let sum = M::op(&M::op(left_sum, &node.value), right_sum);
+        let rev_sum = M::op(&M::op(right_rev, &node.value), left_rev);
+        node.sum = sum;
+        node.rev_sum = rev_sum;
+        node.len = len(&node.left) + len(&node.right) + 1;
+    }
+
+    fn merge(a: Link<M::Item>, b: Link<M::Item>) -> Link<M::Item> {
+        match (a, b) {
+            (None, b) => b,
+            (a, None) => a,
+            (Some(mut a), Some(mut b)) => {
+                if a.priority > b.priority {
+                    a.push();
+                    a.right = Self::merge(a.right.take(), Some(b));
+                    Self::fix(&mut a);
+                    Some(a)
+                } else {
+                    b.push();
+                    b.left = Self::merge(Some(a), b.left.take());
+                    Self::fix(&mut b);
+                    Some(b)
+                }
+            }
+        }
+    }
+
+    /// 先頭`k`個とそれ以外に分ける
+    fn split(link: Link<M::Item>, k: usize) -> (Link<M::Item>, Link<M::Item>) {
+        let Some(mut node) = link else {
+            return (None, None);
+        };
+        node.push();
+        let left_len = len(&node.left);
+        if k <= left_len {
+            let (a, b) = Self::split(node.left.take(), k);
+            node.left = b;
+            Self::fix(&mut node);
+            (a, Some(node))
+        } else {
+            let (a, b) = Self::split(node.right.take(), k - left_len - 1);
+            node.right = a;
+            Self::fix(&mut node);
+            (Some(node), b)
+        }
+    }
+
+    /// `index`番目の前に`value`を挿入する。
+    pub fn insert(&mut self, index: usize, value: M::Item) {
+        assert!(index <= self.len(), "index {index} out of bounds for length {}", self.len());
+        let node = self.new_node(value);
+        let (a, b) = Self::split(self.root.take(), index);
+        self.root = Self::merge(Self::merge(a, Some(node)), b);
+    }
+
+    pub fn push(&mut self, value: M::Item) {
+        let node = self.new_node(value);
+        self.root = Self::merge(self.root.take(), Some(node));
+    }
+
+    pub fn remove(&mut self, index: usize) -> M::Item {
+        assert!(index < self.len(), "index {index} out of bounds for length {}", self.len());
+        let (a, b) = Self::split(self.root.take(), index);
+        let (node, c) = Self::split(b, 1);
+        self.root = Self::merge(a, c);
+        node.unwrap().value
+    }
+
+    fn update_rec(node: &mut Node<M::Item>, index: usize, value: M::Item) {
+        node.push();
+        let left_len = len(&node.left);
+        match index.cmp(&left_len) {
+            Ordering::Less => Self::update_rec(node.left.as_mut().unwrap(), index, value),
+            Ordering::Equal => node.value = value,
+            Ordering::Greater => Self::update_rec(node.right.as_mut().unwrap(), index - left_len - 1, value),
+        }
+        Self::fix(node);
+    }
+
+    pub fn update(&mut self, index: usize, value: M::Item) {
+        assert!(index < self.len(), "index {index} out of bounds for length {}", self.len());
+        Self::update_rec(self.root.as_mut().unwrap(), index, value);
+    }
+
+    pub fn get(&self, mut index: usize) -> &M::Item {
+        assert!(index < self.len(), "index {index} out of bounds for length {}", self.len());
+        let mut link = &self.root;
+        let mut rev = false;
+        loop {
+            let node = link.as_ref().unwrap();
+            let (left, right) = node.children(rev);
+            let left_len = len(left);
+            rev ^= node.rev;
+            match index.cmp(&left_len) {
+                Ordering::Less => link = left,
+                Ordering::Equal => return &node.value,
+                Ordering::Greater => {
+                    index -= left_len + 1;
+                    link = right;
+                }
+            }
+        }
+    }
+
+    /// 区間を反転する。
+    pub fn reverse<R: RangeBounds<usize>>(&mut self, range: R) {
+        let (l, r) = to_range(&range, self.len());
+        assert!(l <= r && r <= self.len(), "range {l}..{r} out of bounds for length {}", self.len());
+        let (a, b) = Self::split(self.root.take(), l);
+        let (mut b, c) = Self::split(b, r - l);
+        if let Some(b) = &mut b {
+            b.toggle();
+        }
+        self.root = Self::merge(Self::merge(a, b), c);
+    }
+
+    /// `at`番目以降を切り離して返す。
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len(), "index {at} out of bounds for length {}", self.len());
+        let (a, b) = Self::split(self.root.take(), at);
+        self.root = a;
+        Self {
+            root: b,
+            seed: self.next_priority(),
+        }
+    }
+
+    /// `other`の要素をすべて末尾に移す。`other`は空になる。
+    pub fn append(&mut self, other: &mut Self) {
+        self.root = Self::merge(self.root.take(), other.root.take());
+    }
+
+    pub fn iter(&self) -> Iter<'_, M::Item> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(&self.root, false);
+        iter
+    }
+}
+
+/// `ImplicitTreap`の要素を先頭から順に返すイテレータ
+pub struct Iter<'a, T> {
+    // まだ値を返していないノードと、そのノードに遅延している反転
+    stack: Vec<(&'a Node<T>, bool)>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn push_left(&mut self, mut link: &'a Link<T>, mut rev: bool) {
+        while let Some(node) = link {
+            self.stack.push((node, rev));
+            link = node.children(rev).0;
+            rev ^= node.rev;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, rev) = self.stack.pop()?;
+        self.push_left(node.children(rev).1, rev ^ node.rev);
+        Some(&node.value)
+    }
+}
+
+impl<'a, M: Monoid> IntoIterator for &'a ImplicitTreap<M> {
+    type Item = &'a M::Item;
+    type IntoIter = Iter<'a, M::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[test]
+fn treap_test() {
+    use crate::monoid::Affine;
+
+    let mut rand = crate::test_util::xorshift(0x2545f4914f6cdd1d);
+    let naive = crate::test_util::affine_fold;
+
+    let mut v: Vec<(i64, i64)> = Vec::new();
+    let mut treap = ImplicitTreap::<Affine<i64>>::new();
+    for _ in 0..3000 {
+        let value = (rand(3) as i64 - 1, rand(10) as i64);
+        let n = v.len();
+        match rand(7) {
+            0 | 1 => {
+                let i = rand(n + 1);
+                v.insert(i, value);
+                treap.insert(i, value);
+            }
+            2 if n > 0 => {
+                let i = rand(n);
+                assert_eq!(v.remove(i), treap.remove(i));
+            }
+            3 if n > 0 => {
+                let i = rand(n);
+                v[i] = value;
+                treap.update(i, value);
+            }
+            4 => {
+                let l = rand(n + 1);
+                let r = l + rand(n + 1 - l);
+                v[l..r].reverse();
+                treap.reverse(l..r);
+            }
+            5 => {
+                let at = rand(n + 1);
+                let mut tail = treap.split_off(at);
+                assert_eq!(&v[at..], &tail.iter().copied().collect::<Vec<_>>()[..]);
+                tail.reverse(..);
+                tail.append(&mut treap);
+                assert!(treap.is_empty());
+                treap = tail;
+                let mut head = v.split_off(at);
+                head.reverse();
+                head.append(&mut v);
+                v = head;
+            }
+            _ => {
+                let l = rand(n + 1);
+                let r = l + rand(n + 1 - l);
+                assert_eq!(naive(&v[l..r]), treap.query(l..r));
+            }
+        }
+        assert_eq!(v.len(), treap.len());
+        if !v.is_empty() {
+            let i = rand(v.len());
+            assert_eq!(&v[i], treap.get(i));
+        }
+    }
+    assert_eq!(v, treap.iter().copied().collect::<Vec<_>>());
+    for l in 0..=v.len().min(40) {
+        for r in l..=v.len().min(40) {
+            assert_eq!(naive(&v[l..r]), treap.query(l..r));
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    fmt,
+    ops::{AddAssign, Bound, RangeBounds, Sub},
+};
+
+use crate::{monoid::Monoid, to_range, BinTreeVec, Indexer, SegTree};
+
+/// 二次元セグメント木
+///
+/// x方向のセグメント木の各ノードに、y方向の`SegTree`を持つ。
+/// 異なるノードの値を順不同で合成するので、`M`は可換である必要がある。
+pub struct SegTree2D<M: Monoid> {
+    columns: BinTreeVec<SegTree<M>>,
+    width: usize,
+    height: usize,
+}
+
+impl<M: Monoid> fmt::Debug for SegTree2D<M>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegTree2D")
+            .field("columns", &self.columns.0)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl<M: Monoid> Clone for SegTree2D<M>
+where
+    M::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            columns: BinTreeVec(self.columns.0.clone()),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl<M: Monoid> SegTree2D<M>
+where
+    M::Item: Clone,
+{
+    pub fn new(width: usize, height: usize) -> Self {
+        let column = std::iter::repeat_with(M::identity).take(height).collect::<Vec<_>>();
+        Self::from_grid(&vec![column; width])
+    }
+
+    /// `grid[x][y]`を初期値とする木を作る。各行の長さは等しくなければならない。
+    pub fn from_grid(grid: &[Vec<M::Item>]) -> Self {
+        let width = grid.len();
+        let height = grid.first().map_or(0, Vec::len);
+        assert!(grid.iter().all(|col| col.len() == height), "all columns must have the same length");
+
+        let width_aligned = width.next_power_of_two();
+        let offset = width_aligned - 1;
+        let mut values = BinTreeVec(vec![Vec::new(); width_aligned * 2 - 1]);
+        for x in 0..width_aligned {
+            values[Indexer(offset + x)] = match grid.get(x) {
+                Some(col) => col.clone(),
+                None => std::iter::repeat_with(M::identity).take(height).collect(),
+            };
+        }
+        for i in (0..offset).rev() {
+            let (ch1, ch2) = Indexer(i).child();
+            values[Indexer(i)] = values[ch1]
+                .iter()
+                .zip(values[ch2].iter())
+                .map(|(a, b)| M::op(a, b))
+                .collect();
+        }
+        let columns = BinTreeVec(values.0.iter().map(|v| SegTree::from_slice(v)).collect());
+
+        Self { columns, width, height }
+    }
+
+    pub fn update(&mut self, (x, y): (usize, usize), value: M::Item) {
+        assert!(x < self.width && y < self.height, "index ({x}, {y}) out of bounds for size ({}, {})", self.width, self.height);
+        let mut idx = Indexer(x + self.columns.len() / 2);
+        self.columns[idx].update(y, value);
+        while !idx.is_root() {
+            idx = idx.parent();
+            let (ch1, ch2) = idx.child();
+            let val = M::op(&self.columns[ch1][y], &self.columns[ch2][y]);
+            self.columns[idx].update(y, val);
+        }
+    }
+}
+
+impl<M: Monoid> SegTree2D<M> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn query<X: RangeBounds<usize>, Y: RangeBounds<usize>>(&self, x_range: X, y_range: Y) -> M::Item {
+        let (xl, xr) = to_range(&x_range, self.width);
+        let (yl, yr) = to_range(&y_range, self.height);
+        assert!(xl <= xr && xr <= self.width, "range {xl}..{xr} out of bounds for width {}", self.width);
+        assert!(yl <= yr && yr <= self.height, "range {yl}..{yr} out of bounds for height {}", self.height);
+        if xl == xr || yl == yr {
+            return M::identity();
+        }
+
+        let offset = self.columns.len() / 2;
+        let mut left = Indexer(offset + xl);
+        // 右端は含まない
+        let mut right = Indexer(offset + xr);
+        let mut result1 = M::identity();
+        let mut result2 = M::identity();
+        while left < right {
+            if left.is_right() {
+                result1 = M::op(&result1, &self.columns[left].query(yl..yr));
+                left += 1;
+            }
+            if right.is_right() {
+                right -= 1;
+                result2 = M::op(&self.columns[right].query(yl..yr), &result2);
+            }
+            left = left.parent();
+            right = right.parent();
+        }
+        M::op(&result1, &result2)
+    }
+}
+
+/// 二次元Fenwick木
+#[derive(Debug, Clone)]
+pub struct Fenwick2D<T> {
+    // 1-indexedで`(x, y)`のノードを`data[(x - 1) * height + (y - 1)]`に持つ
+    data: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: Copy + Default + AddAssign> Fenwick2D<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            data: vec![T::default(); width * height],
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add(&mut self, (x, y): (usize, usize), delta: T) {
+        assert!(x < self.width && y < self.height, "index ({x}, {y}) out of bounds for size ({}, {})", self.width, self.height);
+        let mut i = x + 1;
+        while i <= self.width {
+            let mut j = y + 1;
+            while j <= self.height {
+                self.data[(i - 1) * self.height + (j - 1)] += delta;
+                j += j & j.wrapping_neg();
+            }
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// `(0..x, 0..y)`の和
+    pub fn prefix_sum(&self, x: usize, y: usize) -> T {
+        assert!(x <= self.width && y <= self.height, "index ({x}, {y}) out of bounds for size ({}, {})", self.width, self.height);
+        let mut result = T::default();
+        let mut i = x;
+        while i > 0 {
+            let mut j = y;
+            while j > 0 {
+                result += self.data[(i - 1) * self.height + (j - 1)];
+                j &= j - 1;
+            }
+            i &= i - 1;
+        }
+        result
+    }
+}
+
+impl<T: Copy + Default + AddAssign + Sub<Output = T>> Fenwick2D<T> {
+    pub fn range_sum<X: RangeBounds<usize>, Y: RangeBounds<usize>>(&self, x_range: X, y_range: Y) -> T {
+        let (xl, xr) = to_range(&x_range, self.width);
+        let (yl, yr) = to_range(&y_range, self.height);
+        assert!(xl <= xr && yl <= yr, "range start is greater than end");
+        let mut result = self.prefix_sum(xr, yr);
+        result += self.prefix_sum(xl, yl);
+        result - self.prefix_sum(xl, yr) - self.prefix_sum(xr, yl)
+    }
+}
+
+fn to_index_range<K: Ord, R: RangeBounds<K>>(keys: &[K], range: &R) -> (usize, usize) {
+    let l = match range.start_bound() {
+        Bound::Excluded(l) => keys.partition_point(|k| k <= l),
+        Bound::Included(l) => keys.partition_point(|k| k < l),
+        Bound::Unbounded => 0,
+    };
+    let r = match range.end_bound() {
+        Bound::Excluded(r) => keys.partition_point(|k| k < r),
+        Bound::Included(r) => keys.partition_point(|k| k <= r),
+        Bound::Unbounded => keys.len(),
+    };
+    (l, r.max(l))
+}
+
+/// 座標圧縮した二次元セグメント木
+///
+/// 更新する可能性のある点をすべて先に与えることで、点の個数を`n`として
+/// O(n log n)のメモリで任意の座標を扱える。`M`は可換である必要がある。
+pub struct CompressedSegTree2D<M: Monoid, K> {
+    xs: Vec<K>,
+    // x方向のノードごとに、その区間に含まれる点のy座標と、それに対応する`SegTree`を持つ
+    ys: BinTreeVec<Vec<K>>,
+    columns: BinTreeVec<SegTree<M>>,
+}
+
+impl<M: Monoid, K: fmt::Debug> fmt::Debug for CompressedSegTree2D<M, K>
+where
+    M::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedSegTree2D")
+            .field("xs", &self.xs)
+            .field("ys", &self.ys.0)
+            .field("columns", &self.columns.0)
+            .finish()
+    }
+}
+
+impl<M: Monoid, K: Ord + Copy> CompressedSegTree2D<M, K>
+where
+    M::Item: Clone,
+{
+    /// `points`に含まれる点だけを更新できる、すべての値が単位元の木を作る。
+    pub fn new(points: &[(K, K)]) -> Self {
+        let mut xs = points.iter().map(|&(x, _)| x).collect::<Vec<_>>();
+        xs.sort_unstable();
+        xs.dedup();
+
+        let width_aligned = xs.len().next_power_of_two();
+        let offset = width_aligned - 1;
+        let mut ys = BinTreeVec(vec![Vec::new(); width_aligned * 2 - 1]);
+        for &(x, y) in points {
+            let i = xs.binary_search(&x).unwrap();
+            ys[Indexer(offset + i)].push(y);
+        }
+        for i in (0..(width_aligned * 2 - 1)).rev() {
+            if i < offset {
+                let (ch1, ch2) = Indexer(i).child();
+                let mut merged = [ys[ch1].as_slice(), ys[ch2].as_slice()].concat();
+                merged.sort_unstable();
+                ys[Indexer(i)] = merged;
+            } else {
+                ys[Indexer(i)].sort_unstable();
+            }
+            ys[Indexer(i)].dedup();
+        }
+        let columns = ys
+            .0
+            .iter()
+            .map(|ys| SegTree::from_slice(&std::iter::repeat_with(M::identity).take(ys.len()).collect::<Vec<_>>()))
+            .collect();
+
+        Self {
+            xs,
+            ys,
+            columns: BinTreeVec(columns),
+        }
+    }
+
+    /// # Panics
+    /// `(x, y)`が構築時に与えた点に含まれない場合
+    pub fn update(&mut self, (x, y): (K, K), value: M::Item) {
+        let i = self.xs.binary_search(&x).expect("point was not given on construction");
+        let mut idx = Indexer(i + self.columns.len() / 2);
+        let j = self.ys[idx].binary_search(&y).expect("point was not given on construction");
+        self.columns[idx].update(j, value);
+        while !idx.is_root() {
+            idx = idx.parent();
+            let (ch1, ch2) = idx.child();
+            let get = |c: Indexer| {
+                self.ys[c]
+                    .binary_search(&y)
+                    .map_or_else(|_| M::identity(), |j| self.columns[c][j].clone())
+            };
+            let val = M::op(&get(ch1), &get(ch2));
+            let j = self.ys[idx].binary_search(&y).unwrap();
+            self.columns[idx].update(j, val);
+        }
+    }
+}
+
+impl<M: Monoid, K: Ord> CompressedSegTree2D<M, K> {
+    fn query_column<Y: RangeBounds<K>>(&self, idx: Indexer, y_range: &Y) -> M::Item {
+        let (l, r) = to_index_range(&self.ys[idx], y_range);
+        if l == r {
+            M::identity()
+        } else {
+            self.columns[idx].query(l..r)
+        }
+    }
+
+    pub fn query<X: RangeBounds<K>, Y: RangeBounds<K>>(&self, x_range: X, y_range: Y) -> M::Item {
+        let (xl, xr) = to_index_range(&self.xs, &x_range);
+        let offset = self.columns.len() / 2;
+        let mut left = Indexer(offset + xl);
+        let mut right = Indexer(offset + xr);
+        let mut result1 = M::identity();
+        let mut result2 = M::identity();
+        while left < right {
+            if left.is_right() {
+                result1 = M::op(&result1, &self.query_column(left, &y_range));
+                left += 1;
+            }
+            if right.is_right() {
+                right -= 1;
+                result2 = M::op(&self.query_column(right, &y_range), &result2);
+            }
+            left = left.parent();
+            right = right.parent();
+        }
+        M::op(&result1, &result2)
+    }
+}
+
+#[test]
+fn two_dim_test() {
+    use crate::monoid::{Min, Sum};
+
+    let (w, h) = (5, 7);
+    let mut grid = (0..w)
+        .map(|x| (0..h).map(|y| ((x * 7 + y * 3) % 10) as i64).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut sum = SegTree2D::<Sum<i64>>::from_grid(&grid);
+    let mut min = SegTree2D::<Min<i64>>::from_grid(&grid);
+    let mut fenwick = Fenwick2D::new(w, h);
+    for (x, col) in grid.iter().enumerate() {
+        for (y, &v) in col.iter().enumerate() {
+            fenwick.add((x, y), v);
+        }
+    }
+    for (x, y, v) in [(0, 0, 5), (4, 6, -3), (2, 3, 20), (4, 0, 1)] {
+        fenwick.add((x, y), v - grid[x][y]);
+        grid[x][y] = v;
+        sum.update((x, y), v);
+        min.update((x, y), v);
+        for xl in 0..=w {
+            for xr in xl..=w {
+                for yl in 0..=h {
+                    for yr in yl..=h {
+                        let cells = grid[xl..xr].iter().flat_map(|col| &col[yl..yr]);
+                        assert_eq!(cells.clone().sum::<i64>(), sum.query(xl..xr, yl..yr));
+                        assert_eq!(cells.clone().sum::<i64>(), fenwick.range_sum(xl..xr, yl..yr));
+                        assert_eq!(cells.copied().min().unwrap_or(i64::MAX), min.query(xl..xr, yl..yr));
+                    }
+                }
+            }
+        }
+    }
+
+    let points = [(-1_000_000_000i64, 5i64), (3, -7), (3, 100), (1_000_000_000, 0), (42, 42)];
+    let mut compressed = CompressedSegTree2D::<Sum<i32>, i64>::new(&points);
+    for (i, &p) in points.iter().enumerate() {
+        compressed.update(p, i as i32 + 1);
+    }
+    assert_eq!(15, compressed.query(.., ..));
+    assert_eq!(5, compressed.query(3..=3, ..));
+    assert_eq!(2, compressed.query(3..=3, ..100));
+    assert_eq!(9, compressed.query(0.., 0..50));
+    assert_eq!(1, compressed.query(..0, ..));
+    assert_eq!(0, compressed.query(4..42, ..));
+    compressed.update((3, -7), 10);
+    assert_eq!(23, compressed.query(.., ..));
+}
@@ -0,0 +1,233 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::to_range;
+
+/// rankを定数時間で計算できるビット列
+#[derive(Debug, Clone)]
+struct BitVec {
+    blocks: Vec<u64>,
+    // `ranks[i]`は`blocks[..i]`に含まれる1の個数
+    ranks: Vec<usize>,
+}
+
+impl BitVec {
+    fn from_bits(bits: impl ExactSizeIterator<Item = bool>) -> Self {
+        let mut blocks = vec![0u64; bits.len() / 64 + 1];
+        for (i, b) in bits.enumerate() {
+            blocks[i / 64] |= (b as u64) << (i % 64);
+        }
+        let mut ranks = Vec::with_capacity(blocks.len() + 1);
+        ranks.push(0);
+        for b in &blocks {
+            ranks.push(ranks.last().unwrap() + b.count_ones() as usize);
+        }
+        Self { blocks, ranks }
+    }
+
+    fn get(&self, index: usize) -> bool {
+        (self.blocks[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    /// `..index`に含まれる1の個数
+    fn rank1(&self, index: usize) -> usize {
+        let mask = (1u64 << (index % 64)) - 1;
+        self.ranks[index / 64] + (self.blocks[index / 64] & mask).count_ones() as usize
+    }
+
+    fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+}
+
+/// Wavelet Matrix
+///
+/// 値の種類に関係なく、区間内のk番目に小さい値や、ある範囲の値の個数をO(log(値の最大値))で求められる。
+#[derive(Debug, Clone)]
+pub struct WaveletMatrix {
+    // 上位ビットから順に並べる
+    levels: Vec<BitVec>,
+    // 各段で0が立っている個数
+    zeros: Vec<usize>,
+    len: usize,
+}
+
+fn to_value_range<R: RangeBounds<u64>>(range: &R) -> (u128, u128) {
+    let l = match range.start_bound() {
+        Bound::Excluded(&l) => l as u128 + 1,
+        Bound::Included(&l) => l as u128,
+        Bound::Unbounded => 0,
+    };
+    let r = match range.end_bound() {
+        Bound::Excluded(&r) => r as u128,
+        Bound::Included(&r) => r as u128 + 1,
+        Bound::Unbounded => u64::MAX as u128 + 1,
+    };
+    (l, r)
+}
+
+impl WaveletMatrix {
+    pub fn from_slice(slc: &[u64]) -> Self {
+        let max = slc.iter().copied().max().unwrap_or(0);
+        let bit_len = (u64::BITS - max.leading_zeros()).max(1);
+        let mut cur = slc.to_vec();
+        let mut levels = Vec::with_capacity(bit_len as usize);
+        let mut zeros = Vec::with_capacity(bit_len as usize);
+        for level in (0..bit_len).rev() {
+            let bits = BitVec::from_bits(cur.iter().map(|&x| (x >> level) & 1 == 1));
+            let (mut next, ones): (Vec<_>, Vec<_>) = cur.iter().partition(|&&x| (x >> level) & 1 == 0);
+            zeros.push(next.len());
+            next.extend(ones);
+            levels.push(bits);
+            cur = next;
+        }
+        Self {
+            levels,
+            zeros,
+            len: slc.len(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bit_len(&self) -> usize {
+        self.levels.len()
+    }
+
+    fn to_range<R: RangeBounds<usize>>(&self, range: &R) -> (usize, usize) {
+        let (l, r) = to_range(range, self.len);
+        assert!(l <= r && r <= self.len, "range {l}..{r} out of bounds for length {}", self.len);
+        (l, r)
+    }
+
+    pub fn get(&self, mut index: usize) -> u64 {
+        assert!(index < self.len, "index {index} out of bounds for length {}", self.len);
+        let mut result = 0;
+        for (bits, &zeros) in self.levels.iter().zip(&self.zeros) {
+            result <<= 1;
+            if bits.get(index) {
+                result |= 1;
+                index = zeros + bits.rank1(index);
+            } else {
+                index = bits.rank0(index);
+            }
+        }
+        result
+    }
+
+    /// 区間内の`value`の個数
+    pub fn rank<R: RangeBounds<usize>>(&self, value: u64, range: R) -> usize {
+        let (mut l, mut r) = self.to_range(&range);
+        if value >> (self.bit_len() - 1) > 1 {
+            return 0;
+        }
+        for (level, (bits, &zeros)) in self.levels.iter().zip(&self.zeros).enumerate() {
+            let shift = self.bit_len() - 1 - level;
+            if (value >> shift) & 1 == 1 {
+                (l, r) = (zeros + bits.rank1(l), zeros + bits.rank1(r));
+            } else {
+                (l, r) = (bits.rank0(l), bits.rank0(r));
+            }
+        }
+        r - l
+    }
+
+    /// `k`番目 (0-indexed) の`value`の位置。存在しなければ`None`を返す。
+    pub fn select(&self, value: u64, k: usize) -> Option<usize> {
+        if self.rank(value, ..) <= k {
+            return None;
+        }
+        // `rank(value, ..=i) > k`となる最小の`i`
+        let (mut lo, mut hi) = (0, self.len - 1);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.rank(value, ..=mid) > k {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Some(lo)
+    }
+
+    /// 区間内で`k`番目 (0-indexed) に小さい値
+    pub fn quantile<R: RangeBounds<usize>>(&self, range: R, mut k: usize) -> u64 {
+        let (mut l, mut r) = self.to_range(&range);
+        assert!(k < r - l, "k = {k} is out of range for {} elements", r - l);
+        let mut result = 0;
+        for (bits, &zeros) in self.levels.iter().zip(&self.zeros) {
+            result <<= 1;
+            let (l0, r0) = (bits.rank0(l), bits.rank0(r));
+            if k < r0 - l0 {
+                (l, r) = (l0, r0);
+            } else {
+                k -= r0 - l0;
+                result |= 1;
+                (l, r) = (zeros + (l - l0), zeros + (r - r0));
+            }
+        }
+        result
+    }
+
+    fn count_less(&self, mut l: usize, mut r: usize, value: u128) -> usize {
+        if value >> self.bit_len() > 0 {
+            return r - l;
+        }
+        let mut result = 0;
+        for (level, (bits, &zeros)) in self.levels.iter().zip(&self.zeros).enumerate() {
+            let shift = self.bit_len() - 1 - level;
+            let (l0, r0) = (bits.rank0(l), bits.rank0(r));
+            if (value >> shift) & 1 == 1 {
+                result += r0 - l0;
+                (l, r) = (zeros + (l - l0), zeros + (r - r0));
+            } else {
+                (l, r) = (l0, r0);
+            }
+        }
+        result
+    }
+
+    /// 区間内で、値が`value_range`に含まれる要素の個数
+    pub fn range_freq<R: RangeBounds<usize>, V: RangeBounds<u64>>(&self, range: R, value_range: V) -> usize {
+        let (l, r) = self.to_range(&range);
+        let (lower, upper) = to_value_range(&value_range);
+        if lower >= upper {
+            return 0;
+        }
+        self.count_less(l, r, upper) - self.count_less(l, r, lower)
+    }
+}
+
+#[test]
+fn wavelet_test() {
+    let s = [5u64, 4, 5, 5, 2, 1, 5, 6, 1, 3, 5, 0, 9, 1_000_000_007];
+    let wm = WaveletMatrix::from_slice(&s);
+    for (i, &v) in s.iter().enumerate() {
+        assert_eq!(v, wm.get(i));
+    }
+    for l in 0..=s.len() {
+        for r in l..=s.len() {
+            let mut sorted = s[l..r].to_vec();
+            sorted.sort();
+            for (k, &v) in sorted.iter().enumerate() {
+                assert_eq!(v, wm.quantile(l..r, k));
+            }
+            for v in [0, 1, 5, 6, 7, 1_000_000_007, u64::MAX] {
+                assert_eq!(s[l..r].iter().filter(|&&x| x == v).count(), wm.rank(v, l..r));
+                assert_eq!(s[l..r].iter().filter(|&&x| x < v).count(), wm.range_freq(l..r, ..v));
+                assert_eq!(s[l..r].iter().filter(|&&x| (1..=v).contains(&x)).count(), wm.range_freq(l..r, 1..=v));
+            }
+        }
+    }
+    assert_eq!(Some(0), wm.select(5, 0));
+    assert_eq!(Some(3), wm.select(5, 2));
+    assert_eq!(Some(10), wm.select(5, 4));
+    assert_eq!(None, wm.select(5, 5));
+    assert_eq!(None, wm.select(7, 0));
+    assert_eq!(Some(13), wm.select(1_000_000_007, 0));
+}
@@ -0,0 +1,881 @@
+//! ランダムな操作列に対して、各データ構造を素朴な`Vec`などの実装と比較する。
+
+use std::{
+    collections::BTreeMap,
+    ops::{
+        Bound::{self, Excluded, Included, Unbounded},
+        Range, RangeBounds,
+    },
+};
+
+use proptest::{prelude::*, sample::Index, test_runner::TestCaseError};
+use segtree::{
+    monoid::{Add, Affine, Min, Sum},
+    persistent::Version,
+    CompactSegTree, CompressedSegTree2D, DynamicSegTree, Fenwick, Fenwick2D, HldLazySegTree, HldSegTree, ImplicitTreap,
+    LazySegTree, LiChaoTree, MergeSortTree, PersistentSegTree, RangeFenwick, SegTree, SegTree2D, SegTreeBeats, SparseTable,
+    WaveletMatrix,
+};
+
+#[allow(dead_code)]
+#[path = "../src/test_util.rs"]
+mod test_util;
+use test_util::affine_fold as naive_affine;
+
+type Affine64 = (i64, i64);
+
+// 傾きを-1, 0, 1に限ることで、長い列を合成してもオーバーフローしない
+fn affine() -> impl Strategy<Value = Affine64> + Clone {
+    (-1i64..=1, -100i64..=100)
+}
+
+/// 0、1、2の冪、2の冪+1を重点的に含む長さ
+fn edge_len() -> impl Strategy<Value = usize> {
+    prop_oneof![
+        prop::sample::select(vec![0usize, 1, 2, 3, 4, 5, 8, 9, 16, 17, 32, 33, 64, 65]),
+        0usize..100,
+    ]
+}
+
+fn initial<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<T::Value>> {
+    edge_len().prop_flat_map(move |len| prop::collection::vec(value.clone(), len))
+}
+
+// 長さが操作ごとに変わるので、添字は`Index`で持っておき、使うときに範囲に収める
+fn to_range(len: usize, l: &Index, r: &Index) -> (usize, usize) {
+    let (l, r) = (l.index(len + 1), r.index(len + 1));
+    (l.min(r), l.max(r))
+}
+
+fn range() -> impl Strategy<Value = (Index, Index)> {
+    (any::<Index>(), any::<Index>())
+}
+
+#[derive(Debug, Clone)]
+enum SegTreeOp<T> {
+    Update(Index, T),
+    Query(Index, Index),
+    Push(T),
+    UpdateBatch(Vec<(Index, T)>),
+}
+
+fn segtree_ops<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<SegTreeOp<T::Value>>> {
+    let op = prop_oneof![
+        3 => (any::<Index>(), value.clone()).prop_map(|(i, x)| SegTreeOp::Update(i, x)),
+        3 => range().prop_map(|(l, r)| SegTreeOp::Query(l, r)),
+        1 => value.clone().prop_map(SegTreeOp::Push),
+        1 => prop::collection::vec((any::<Index>(), value), 0..8).prop_map(SegTreeOp::UpdateBatch),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum LeavesOp<T> {
+    // 1つのガードでまとめて書き換える
+    Set(Vec<(Index, T)>),
+    Fill(Index, Index, T),
+    All(T),
+    Extend(Vec<T>),
+    Query(Index, Index),
+}
+
+fn leaves_ops<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<LeavesOp<T::Value>>> {
+    let op = prop_oneof![
+        2 => prop::collection::vec((any::<Index>(), value.clone()), 0..16).prop_map(LeavesOp::Set),
+        1 => (range(), value.clone()).prop_map(|((l, r), x)| LeavesOp::Fill(l, r, x)),
+        1 => value.clone().prop_map(LeavesOp::All),
+        1 => prop::collection::vec(value, 0..8).prop_map(LeavesOp::Extend),
+        2 => range().prop_map(|(l, r)| LeavesOp::Query(l, r)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum PointOp<T> {
+    Update(Index, T),
+    Query(Index, Index),
+}
+
+fn point_ops<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<PointOp<T::Value>>> {
+    let op = prop_oneof![
+        (any::<Index>(), value).prop_map(|(i, x)| PointOp::Update(i, x)),
+        range().prop_map(|(l, r)| PointOp::Query(l, r)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum LazyOp {
+    Apply(Index, Index, i64),
+    Query(Index, Index),
+}
+
+fn lazy_ops() -> impl Strategy<Value = Vec<LazyOp>> {
+    let op = prop_oneof![
+        (range(), -100i64..100).prop_map(|((l, r), x)| LazyOp::Apply(l, r, x)),
+        range().prop_map(|(l, r)| LazyOp::Query(l, r)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum TreapOp<T> {
+    Update(Index, T),
+    Query(Index, Index),
+    Insert(Index, T),
+    Remove(Index),
+    Reverse(Index, Index),
+    // `split_off`した後半の後ろに前半を`append`する
+    Rotate(Index),
+}
+
+fn treap_ops<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<TreapOp<T::Value>>> {
+    let op = prop_oneof![
+        (any::<Index>(), value.clone()).prop_map(|(i, x)| TreapOp::Update(i, x)),
+        range().prop_map(|(l, r)| TreapOp::Query(l, r)),
+        (any::<Index>(), value).prop_map(|(i, x)| TreapOp::Insert(i, x)),
+        any::<Index>().prop_map(TreapOp::Remove),
+        range().prop_map(|(l, r)| TreapOp::Reverse(l, r)),
+        any::<Index>().prop_map(TreapOp::Rotate),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum PersistentOp<T> {
+    // 更新元のバージョン、添字、値
+    Update(Index, Index, T),
+    Query(Index, Index, Index),
+}
+
+fn persistent_ops<T: Strategy + Clone>(value: T) -> impl Strategy<Value = Vec<PersistentOp<T::Value>>> {
+    let op = prop_oneof![
+        (any::<Index>(), any::<Index>(), value).prop_map(|(v, i, x)| PersistentOp::Update(v, i, x)),
+        (any::<Index>(), range()).prop_map(|(v, (l, r))| PersistentOp::Query(v, l, r)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+// 端の値を重点的に含む値
+fn extreme() -> impl Strategy<Value = i64> + Clone {
+    prop_oneof![
+        prop::sample::select(vec![i64::MIN, i64::MIN + 1, i64::MAX - 1, i64::MAX, -1, 0, 1]),
+        -100i64..100,
+        any::<i64>(),
+    ]
+}
+
+#[derive(Debug, Clone)]
+enum BeatsOp {
+    Chmin(Index, Index, i64),
+    Chmax(Index, Index, i64),
+    Add(Index, Index, i64),
+    Query(Index, Index),
+}
+
+fn beats_ops() -> impl Strategy<Value = Vec<BeatsOp>> {
+    let op = prop_oneof![
+        (any::<Index>(), any::<Index>(), extreme()).prop_map(|(l, r, x)| BeatsOp::Chmin(l, r, x)),
+        (any::<Index>(), any::<Index>(), extreme()).prop_map(|(l, r, x)| BeatsOp::Chmax(l, r, x)),
+        (any::<Index>(), any::<Index>(), -100i64..100).prop_map(|(l, r, x)| BeatsOp::Add(l, r, x)),
+        (any::<Index>(), any::<Index>()).prop_map(|(l, r)| BeatsOp::Query(l, r)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum DynamicOp {
+    Update(i64, Affine64),
+    Query(i64, i64),
+}
+
+fn dynamic_ops() -> impl Strategy<Value = Vec<DynamicOp>> {
+    let op = prop_oneof![
+        (extreme(), affine()).prop_map(|(i, x)| DynamicOp::Update(i, x)),
+        (extreme(), extreme()).prop_map(|(l, r)| DynamicOp::Query(l.min(r), l.max(r))),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+// 頂点`i`の親を`0..i`から選んだ木の隣接リストと根
+fn tree() -> impl Strategy<Value = (Vec<Vec<usize>>, usize)> {
+    (1usize..40).prop_flat_map(|n| (prop::collection::vec(any::<Index>(), n - 1), 0..n)).prop_map(|(parents, root)| {
+        let mut adj = vec![Vec::new(); parents.len() + 1];
+        for (i, p) in parents.iter().enumerate() {
+            let (u, v) = (i + 1, p.index(i + 1));
+            adj[u].push(v);
+            adj[v].push(u);
+        }
+        (adj, root)
+    })
+}
+
+// `root`を根としたときの各頂点の親と深さ
+fn rooted(adj: &[Vec<usize>], root: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut parent = vec![usize::MAX; adj.len()];
+    let mut depth = vec![0; adj.len()];
+    parent[root] = root;
+    let mut stack = vec![root];
+    while let Some(v) = stack.pop() {
+        for &u in &adj[v] {
+            if parent[u] == usize::MAX {
+                parent[u] = v;
+                depth[u] = depth[v] + 1;
+                stack.push(u);
+            }
+        }
+    }
+    (parent, depth)
+}
+
+// `u`から`v`へのパス上の頂点を順に並べる
+fn naive_path(parent: &[usize], depth: &[usize], mut u: usize, mut v: usize) -> Vec<usize> {
+    let (mut up, mut down) = (Vec::new(), Vec::new());
+    while u != v {
+        if depth[u] >= depth[v] {
+            up.push(u);
+            u = parent[u];
+        } else {
+            down.push(v);
+            v = parent[v];
+        }
+    }
+    up.push(u);
+    up.extend(down.into_iter().rev());
+    up
+}
+
+// `v`の部分木の頂点。根は自身を親に持つ
+fn naive_subtree(parent: &[usize], v: usize) -> Vec<usize> {
+    let in_subtree = |mut u: usize| {
+        while u != v && parent[u] != u {
+            u = parent[u];
+        }
+        u == v
+    };
+    (0..parent.len()).filter(|&u| in_subtree(u)).collect()
+}
+
+#[derive(Debug, Clone)]
+enum HldOp {
+    Update(Index, Affine64),
+    PathAdd(Index, Index, i64),
+    SubtreeAdd(Index, i64),
+    Path(Index, Index),
+    Subtree(Index),
+}
+
+fn hld_ops() -> impl Strategy<Value = Vec<HldOp>> {
+    let op = prop_oneof![
+        (any::<Index>(), affine()).prop_map(|(v, x)| HldOp::Update(v, x)),
+        (any::<Index>(), any::<Index>(), -100i64..100).prop_map(|(u, v, x)| HldOp::PathAdd(u, v, x)),
+        (any::<Index>(), -100i64..100).prop_map(|(v, x)| HldOp::SubtreeAdd(v, x)),
+        (any::<Index>(), any::<Index>()).prop_map(|(u, v)| HldOp::Path(u, v)),
+        any::<Index>().prop_map(HldOp::Subtree),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+#[derive(Debug, Clone)]
+enum LiChaoOp {
+    Line((i64, i64)),
+    // 両端を含む区間
+    Segment(i64, i64, (i64, i64)),
+    Query(Index),
+}
+
+fn li_chao_ops() -> impl Strategy<Value = Vec<LiChaoOp>> {
+    let line = || (-100i64..100, -1000i64..1000);
+    let op = prop_oneof![
+        1 => line().prop_map(LiChaoOp::Line),
+        1 => (-60i64..60, -60i64..60, line()).prop_map(|(l, r, line)| LiChaoOp::Segment(l.min(r), l.max(r), line)),
+        2 => any::<Index>().prop_map(LiChaoOp::Query),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+// 重複が多くなるように小さい値を多めにする
+fn small_u64() -> impl Strategy<Value = u64> + Clone {
+    prop_oneof![3 => 0u64..8, 1 => Just(u64::MAX), 1 => any::<u64>()]
+}
+
+#[derive(Debug, Clone)]
+enum OrderOp {
+    Quantile(Index, Index, Index),
+    Freq(Index, Index, u64, u64),
+}
+
+fn order_op() -> impl Strategy<Value = OrderOp> {
+    prop_oneof![
+        (range(), any::<Index>()).prop_map(|((l, r), k)| OrderOp::Quantile(l, r, k)),
+        (range(), small_u64(), small_u64()).prop_map(|((l, r), a, b)| OrderOp::Freq(l, r, a, b)),
+    ]
+}
+
+// 値の範囲の端の種類を変えて試す
+fn value_bounds(a: u64, b: u64) -> [(Bound<u64>, Bound<u64>); 3] {
+    [(Included(a), Excluded(b)), (Included(a), Included(b)), (Excluded(a), Unbounded)]
+}
+
+// `OrderOp`を実行して、素朴に計算した結果と比べる
+fn check_order(
+    model: &[u64],
+    op: &OrderOp,
+    quantile: impl Fn(Range<usize>, usize) -> u64,
+    range_freq: impl Fn(Range<usize>, (Bound<u64>, Bound<u64>)) -> usize,
+) -> Result<(), TestCaseError> {
+    match op {
+        OrderOp::Quantile(l, r, k) => {
+            let (l, r) = to_range(model.len(), l, r);
+            if l < r {
+                let mut sorted = model[l..r].to_vec();
+                sorted.sort_unstable();
+                let k = k.index(r - l);
+                prop_assert_eq!(sorted[k], quantile(l..r, k));
+            }
+        }
+        &OrderOp::Freq(ref l, ref r, a, b) => {
+            let (l, r) = to_range(model.len(), l, r);
+            for bounds in value_bounds(a, b) {
+                let expected = model[l..r].iter().filter(|v| bounds.contains(v)).count();
+                prop_assert_eq!(expected, range_freq(l..r, bounds));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum WaveletOp {
+    Order(OrderOp),
+    Get(Index),
+    Rank(u64, Index, Index),
+    Select(u64, usize),
+}
+
+fn wavelet_ops() -> impl Strategy<Value = Vec<WaveletOp>> {
+    let op = prop_oneof![
+        2 => order_op().prop_map(WaveletOp::Order),
+        1 => any::<Index>().prop_map(WaveletOp::Get),
+        1 => (small_u64(), range()).prop_map(|(x, (l, r))| WaveletOp::Rank(x, l, r)),
+        1 => (small_u64(), 0usize..8).prop_map(|(x, k)| WaveletOp::Select(x, k)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+// 0、1、2の冪、2の冪+1を含む二次元の大きさ
+fn grid() -> impl Strategy<Value = Vec<Vec<i64>>> {
+    let size = || prop::sample::select(vec![0usize, 1, 2, 3, 4, 5, 8, 9, 16, 17]);
+    (size(), size()).prop_flat_map(|(w, h)| prop::collection::vec(prop::collection::vec(-100i64..100, h), w))
+}
+
+#[derive(Debug, Clone)]
+enum GridOp {
+    Update(Index, Index, i64),
+    Query((Index, Index), (Index, Index)),
+}
+
+fn grid_ops() -> impl Strategy<Value = Vec<GridOp>> {
+    let op = prop_oneof![
+        (any::<Index>(), any::<Index>(), -100i64..100).prop_map(|(x, y, v)| GridOp::Update(x, y, v)),
+        (range(), range()).prop_map(|(x, y)| GridOp::Query(x, y)),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+fn naive_grid(grid: &[Vec<i64>], (xl, xr): (usize, usize), (yl, yr): (usize, usize)) -> i64 {
+    grid[xl..xr].iter().map(|col| col[yl..yr].iter().sum::<i64>()).sum()
+}
+
+// 座標の重複と端の値を含む点
+fn point() -> impl Strategy<Value = (i64, i64)> {
+    let key = || prop_oneof![3 => -4i64..4, 1 => prop::sample::select(vec![i64::MIN, i64::MAX])];
+    (key(), key())
+}
+
+#[derive(Debug, Clone)]
+enum CompressedOp {
+    Update(Index, i64),
+    Query((i64, i64), (i64, i64)),
+}
+
+fn compressed_ops() -> impl Strategy<Value = Vec<CompressedOp>> {
+    let op = prop_oneof![
+        (any::<Index>(), -100i64..100).prop_map(|(i, v)| CompressedOp::Update(i, v)),
+        (point(), point()).prop_map(|(a, b)| CompressedOp::Query((a.0.min(b.0), a.0.max(b.0)), (a.1.min(b.1), a.1.max(b.1)))),
+    ];
+    prop::collection::vec(op, 0..64)
+}
+
+proptest! {
+    #[test]
+    fn segtree_matches_model(mut model in initial(affine()), ops in segtree_ops(affine())) {
+        let mut segtree = SegTree::<Affine<i64>>::from_slice(&model);
+        for op in ops {
+            match op {
+                SegTreeOp::Update(i, x) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    model[i] = x;
+                    segtree.update(i, x);
+                }
+                SegTreeOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(naive_affine(&model[l..r]), segtree.query(l..r));
+                }
+                SegTreeOp::Push(x) => {
+                    model.push(x);
+                    segtree.push(x);
+                }
+                SegTreeOp::UpdateBatch(updates) if !model.is_empty() => {
+                    let updates = updates.into_iter().map(|(i, x)| (i.index(model.len()), x)).collect::<Vec<_>>();
+                    for &(i, x) in &updates {
+                        model[i] = x;
+                    }
+                    segtree.update_batch(&updates);
+                }
+                _ => {}
+            }
+            prop_assert_eq!(&model[..], &segtree[..]);
+            prop_assert_eq!(naive_affine(&model), segtree.query(..));
+        }
+    }
+
+    #[test]
+    fn leaves_mut_matches_model(mut model in initial(affine()), ops in leaves_ops(affine())) {
+        let mut segtree = SegTree::<Affine<i64>>::from_slice(&model);
+        for op in ops {
+            match op {
+                LeavesOp::Set(writes) if !model.is_empty() => {
+                    let mut leaves = segtree.leaves_mut();
+                    for (i, x) in writes {
+                        let i = i.index(model.len());
+                        model[i] = x;
+                        leaves[i] = x;
+                    }
+                }
+                LeavesOp::Fill(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].fill(x);
+                    segtree.leaves_mut()[l..r].fill(x);
+                }
+                LeavesOp::All(x) => {
+                    model.fill(x);
+                    segtree.leaves_mut().iter_mut().for_each(|v| *v = x);
+                }
+                LeavesOp::Extend(values) => {
+                    model.extend(&values);
+                    segtree.extend(values);
+                }
+                LeavesOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(naive_affine(&model[l..r]), segtree.query(l..r));
+                }
+                _ => {}
+            }
+            prop_assert_eq!(&model[..], &segtree[..]);
+            prop_assert_eq!(naive_affine(&model), segtree.query(..));
+        }
+    }
+
+    #[test]
+    fn binary_search_matches_model(model in initial(0u64..100), l in any::<Index>(), limit in 0u64..2000) {
+        let segtree = SegTree::<Sum<u64>>::from_slice(&model);
+        let l = l.index(model.len() + 1);
+        let expected = (l..=model.len()).rev().find(|&r| model[l..r].iter().sum::<u64>() <= limit).unwrap();
+        prop_assert_eq!(expected, segtree.max_right(l, |&s| s <= limit));
+        let r = l;
+        let expected = (0..=r).find(|&l| model[l..r].iter().sum::<u64>() <= limit).unwrap();
+        prop_assert_eq!(expected, segtree.min_left(r, |&s| s <= limit));
+    }
+
+    #[test]
+    fn compact_matches_model(mut model in initial(affine()), ops in point_ops(affine())) {
+        let mut segtree = CompactSegTree::<Affine<i64>>::from_slice(&model);
+        for op in ops {
+            match op {
+                PointOp::Update(i, x) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    model[i] = x;
+                    segtree.update(i, x);
+                }
+                PointOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(naive_affine(&model[l..r]), segtree.query(l..r));
+                }
+                _ => {}
+            }
+        }
+        prop_assert_eq!(&model[..], &segtree[..]);
+    }
+
+    #[test]
+    fn fenwick_matches_model(mut model in initial(-100i64..100), ops in point_ops(-100i64..100)) {
+        let mut fenwick = Fenwick::from_slice(&model);
+        for op in ops {
+            match op {
+                // 差分を加算して値を置き換える
+                PointOp::Update(i, x) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    fenwick.add(i, x - model[i]);
+                    model[i] = x;
+                }
+                PointOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(model[l..r].iter().sum::<i64>(), fenwick.range_sum(l..r));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn range_fenwick_matches_model(mut model in initial(-100i64..100), ops in lazy_ops()) {
+        let mut fenwick = RangeFenwick::from_slice(&model);
+        for op in ops {
+            match op {
+                LazyOp::Apply(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].iter_mut().for_each(|v| *v += x);
+                    fenwick.add(l..r, x);
+                }
+                LazyOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(model[l..r].iter().sum::<i64>(), fenwick.range_sum(l..r));
+                }
+            }
+        }
+        for r in 0..=model.len() {
+            prop_assert_eq!(model[..r].iter().sum::<i64>(), fenwick.prefix_sum(r));
+        }
+    }
+
+    #[test]
+    fn lazy_matches_model(mut model in initial(-100i64..100), ops in lazy_ops()) {
+        let mut segtree = LazySegTree::<Sum<i64>, Add<i64>>::from_slice(&model);
+        for op in ops {
+            match op {
+                LazyOp::Apply(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].iter_mut().for_each(|v| *v += x);
+                    segtree.apply(l..r, x);
+                }
+                LazyOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(model[l..r].iter().sum::<i64>(), segtree.query(l..r));
+                }
+            }
+        }
+        for (i, &v) in model.iter().enumerate() {
+            prop_assert_eq!(v, segtree.get(i));
+        }
+    }
+
+    #[test]
+    fn treap_matches_model(mut model in initial(affine()), ops in treap_ops(affine())) {
+        let mut treap = ImplicitTreap::<Affine<i64>>::from_slice(&model);
+        for op in ops {
+            match op {
+                TreapOp::Update(i, x) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    model[i] = x;
+                    treap.update(i, x);
+                }
+                TreapOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(naive_affine(&model[l..r]), treap.query(l..r));
+                }
+                TreapOp::Insert(i, x) => {
+                    let i = i.index(model.len() + 1);
+                    model.insert(i, x);
+                    treap.insert(i, x);
+                }
+                TreapOp::Remove(i) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    prop_assert_eq!(model.remove(i), treap.remove(i));
+                }
+                TreapOp::Reverse(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].reverse();
+                    treap.reverse(l..r);
+                }
+                TreapOp::Rotate(i) => {
+                    let i = i.index(model.len() + 1);
+                    let mut tail = treap.split_off(i);
+                    prop_assert_eq!(i, treap.len());
+                    prop_assert_eq!(naive_affine(&model[..i]), treap.query(..));
+                    prop_assert_eq!(naive_affine(&model[i..]), tail.query(..));
+                    tail.append(&mut treap);
+                    prop_assert!(treap.is_empty());
+                    treap = tail;
+                    model.rotate_left(i);
+                }
+                _ => {}
+            }
+            prop_assert_eq!(model.len(), treap.len());
+        }
+        prop_assert_eq!(model, treap.iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn beats_matches_model(mut model in initial(extreme()), ops in beats_ops()) {
+        let mut beats = SegTreeBeats::from_slice(&model);
+        for op in ops {
+            match op {
+                BeatsOp::Chmin(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].iter_mut().for_each(|v| *v = (*v).min(x));
+                    beats.chmin(l..r, x);
+                }
+                BeatsOp::Chmax(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    model[l..r].iter_mut().for_each(|v| *v = (*v).max(x));
+                    beats.chmax(l..r, x);
+                }
+                // 要素がオーバーフローする加算は行わない
+                BeatsOp::Add(l, r, x) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    if model[l..r].iter().all(|v| v.checked_add(x).is_some()) {
+                        model[l..r].iter_mut().for_each(|v| *v += x);
+                        beats.add(l..r, x);
+                    }
+                }
+                BeatsOp::Query(l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    let slc = &model[l..r];
+                    prop_assert_eq!(slc.iter().fold(0i64, |s, &v| s.wrapping_add(v)), beats.query_sum(l..r));
+                    prop_assert_eq!(slc.iter().copied().max().unwrap_or(i64::MIN), beats.query_max(l..r));
+                    prop_assert_eq!(slc.iter().copied().min().unwrap_or(i64::MAX), beats.query_min(l..r));
+                }
+            }
+        }
+        prop_assert_eq!(model.iter().fold(0i64, |s, &v| s.wrapping_add(v)), beats.query_sum(..));
+    }
+
+    #[test]
+    fn hld_matches_model((adj, root) in tree(), ops in hld_ops()) {
+        let n = adj.len();
+        let (parent, depth) = rooted(&adj, root);
+        let mut values = (0..n as i64).map(|i| (1, i)).collect::<Vec<_>>();
+        let mut sums = (0..n as i64).collect::<Vec<_>>();
+        let mut tree = HldSegTree::<Affine<i64>>::new(&adj, root, &values);
+        let mut lazy = HldLazySegTree::<Sum<i64>, Add<i64>>::new(&adj, root, &sums);
+        for op in ops {
+            match op {
+                HldOp::Update(v, x) => {
+                    let v = v.index(n);
+                    values[v] = x;
+                    tree.update(v, x);
+                }
+                HldOp::PathAdd(u, v, x) => {
+                    let (u, v) = (u.index(n), v.index(n));
+                    naive_path(&parent, &depth, u, v).into_iter().for_each(|w| sums[w] += x);
+                    lazy.path_update(u, v, x);
+                }
+                HldOp::SubtreeAdd(v, x) => {
+                    let v = v.index(n);
+                    naive_subtree(&parent, v).into_iter().for_each(|u| sums[u] += x);
+                    lazy.subtree_update(v, x);
+                }
+                HldOp::Path(u, v) => {
+                    let (u, v) = (u.index(n), v.index(n));
+                    let path = naive_path(&parent, &depth, u, v);
+                    prop_assert_eq!(naive_affine(&path.iter().map(|&w| values[w]).collect::<Vec<_>>()), tree.path_query(u, v));
+                    prop_assert_eq!(path.iter().map(|&w| sums[w]).sum::<i64>(), lazy.path_query(u, v));
+                }
+                HldOp::Subtree(v) => {
+                    let v = v.index(n);
+                    let hld = tree.hld();
+                    // 部分木は連続した区間に並び、位置の順に集約される
+                    let mut vs = naive_subtree(&parent, v);
+                    vs.sort_by_key(|&u| hld.pos(u));
+                    prop_assert_eq!(hld.subtree_range(v).collect::<Vec<_>>(), vs.iter().map(|&u| hld.pos(u)).collect::<Vec<_>>());
+                    prop_assert_eq!(naive_affine(&vs.iter().map(|&u| values[u]).collect::<Vec<_>>()), tree.subtree_query(v));
+                    prop_assert_eq!(vs.iter().map(|&u| sums[u]).sum::<i64>(), lazy.subtree_query(v));
+                }
+            }
+        }
+        for v in 0..n {
+            prop_assert_eq!(&values[v], tree.get(v));
+            prop_assert_eq!(sums[v], lazy.get(v));
+        }
+    }
+
+    #[test]
+    fn li_chao_matches_model(xs in prop::collection::vec(-50i64..50, 1..32), ops in li_chao_ops()) {
+        let mut compressed = LiChaoTree::new(&xs);
+        let mut range = LiChaoTree::with_range(-50, 49);
+        // 直線と、それを追加した両端を含む区間
+        let mut lines = Vec::new();
+        let expected = |lines: &[(i64, i64, (i64, i64))], x: i64| {
+            lines.iter().filter(|(l, r, _)| (l..=r).contains(&&x)).map(|&(_, _, (a, b))| a * x + b).min()
+        };
+        for op in ops {
+            match op {
+                LiChaoOp::Line(line) => {
+                    lines.push((i64::MIN, i64::MAX, line));
+                    compressed.add_line(line);
+                    range.add_line(line);
+                }
+                LiChaoOp::Segment(l, r, line) => {
+                    lines.push((l, r, line));
+                    compressed.add_segment(l..=r, line);
+                    range.add_segment(l..=r, line);
+                }
+                LiChaoOp::Query(i) => {
+                    let x = xs[i.index(xs.len())];
+                    prop_assert_eq!(expected(&lines, x), compressed.query(x));
+                    prop_assert_eq!(expected(&lines, x), range.query(x));
+                    let x = -50 + i.index(100) as i64;
+                    prop_assert_eq!(expected(&lines, x), range.query(x));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_table_matches_model(model in initial(any::<i64>()), queries in prop::collection::vec(range(), 0..64)) {
+        let table = SparseTable::<Min<i64>>::from_slice(&model);
+        for (l, r) in queries {
+            let (l, r) = to_range(model.len(), &l, &r);
+            prop_assert_eq!(model[l..r].iter().copied().min().unwrap_or(i64::MAX), table.query(l..r));
+        }
+    }
+
+    #[test]
+    fn persistent_matches_model(initial in initial(affine()), ops in persistent_ops(affine())) {
+        let mut segtree = PersistentSegTree::<Affine<i64>>::from_slice(&initial);
+        let mut versions: Vec<(Version, Vec<Affine64>)> = vec![(PersistentSegTree::<Affine<i64>>::initial(), initial)];
+        for op in ops {
+            match op {
+                PersistentOp::Update(v, i, x) if !segtree.is_empty() => {
+                    let (version, model) = &versions[v.index(versions.len())];
+                    let i = i.index(model.len());
+                    let mut model = model.clone();
+                    model[i] = x;
+                    let version = segtree.update(*version, i, x);
+                    prop_assert_eq!(segtree.latest(), version);
+                    versions.push((version, model));
+                }
+                PersistentOp::Query(v, l, r) => {
+                    let (version, model) = &versions[v.index(versions.len())];
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(naive_affine(&model[l..r]), segtree.query(*version, l..r));
+                }
+                _ => {}
+            }
+        }
+        // 古いバージョンも書き換わっていない
+        for (version, model) in &versions {
+            for (i, x) in model.iter().enumerate() {
+                prop_assert_eq!(x, segtree.get(*version, i));
+            }
+        }
+    }
+
+    #[test]
+    fn dynamic_matches_model(ops in dynamic_ops()) {
+        let mut segtree = DynamicSegTree::<Affine<i64>>::new(..);
+        let mut model = BTreeMap::new();
+        for op in ops {
+            match op {
+                DynamicOp::Update(i, x) => {
+                    model.insert(i, x);
+                    segtree.update(i, x);
+                }
+                DynamicOp::Query(l, r) => {
+                    let expected = naive_affine(&model.range(l..=r).map(|(_, &x)| x).collect::<Vec<_>>());
+                    prop_assert_eq!(expected, segtree.query(l..=r));
+                    prop_assert_eq!(model.get(&l).copied().unwrap_or((1, 0)), segtree.get(l));
+                }
+            }
+        }
+        prop_assert_eq!(naive_affine(&model.values().copied().collect::<Vec<_>>()), segtree.query(..));
+    }
+
+    #[test]
+    fn wavelet_matches_model(model in initial(small_u64()), ops in wavelet_ops()) {
+        let wavelet = WaveletMatrix::from_slice(&model);
+        prop_assert_eq!(model.len(), wavelet.len());
+        for op in ops {
+            match op {
+                WaveletOp::Order(op) => {
+                    check_order(&model, &op, |range, k| wavelet.quantile(range, k), |range, bounds| wavelet.range_freq(range, bounds))?;
+                }
+                WaveletOp::Get(i) if !model.is_empty() => {
+                    let i = i.index(model.len());
+                    prop_assert_eq!(model[i], wavelet.get(i));
+                }
+                WaveletOp::Rank(x, l, r) => {
+                    let (l, r) = to_range(model.len(), &l, &r);
+                    prop_assert_eq!(model[l..r].iter().filter(|&&v| v == x).count(), wavelet.rank(x, l..r));
+                }
+                WaveletOp::Select(x, k) => {
+                    let expected = model.iter().enumerate().filter(|&(_, &v)| v == x).nth(k).map(|(i, _)| i);
+                    prop_assert_eq!(expected, wavelet.select(x, k));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn merge_sort_tree_matches_model(model in initial(small_u64()), ops in prop::collection::vec(order_op(), 0..64)) {
+        let tree = MergeSortTree::from_slice(&model);
+        prop_assert_eq!(model.len(), tree.len());
+        for op in ops {
+            check_order(&model, &op, |range, k| *tree.quantile(range, k), |range, bounds| tree.range_freq(range, bounds))?;
+        }
+    }
+
+    #[test]
+    fn segtree_2d_matches_model(mut grid in grid(), ops in grid_ops()) {
+        let mut segtree = SegTree2D::<Sum<i64>>::from_grid(&grid);
+        let (width, height) = (grid.len(), grid.first().map_or(0, Vec::len));
+        let mut fenwick = Fenwick2D::new(width, height);
+        for (x, col) in grid.iter().enumerate() {
+            for (y, &v) in col.iter().enumerate() {
+                fenwick.add((x, y), v);
+            }
+        }
+        for op in ops {
+            match op {
+                GridOp::Update(x, y, v) if width > 0 && height > 0 => {
+                    let (x, y) = (x.index(width), y.index(height));
+                    fenwick.add((x, y), v - grid[x][y]);
+                    grid[x][y] = v;
+                    segtree.update((x, y), v);
+                }
+                GridOp::Query((xl, xr), (yl, yr)) => {
+                    let (xl, xr) = to_range(width, &xl, &xr);
+                    let (yl, yr) = to_range(height, &yl, &yr);
+                    let expected = naive_grid(&grid, (xl, xr), (yl, yr));
+                    prop_assert_eq!(expected, segtree.query(xl..xr, yl..yr));
+                    prop_assert_eq!(expected, fenwick.range_sum(xl..xr, yl..yr));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_2d_matches_model(points in prop::collection::vec(point(), 0..32), ops in compressed_ops()) {
+        let mut segtree = CompressedSegTree2D::<Sum<i64>, i64>::new(&points);
+        let mut model = BTreeMap::new();
+        for op in ops {
+            match op {
+                CompressedOp::Update(i, v) if !points.is_empty() => {
+                    let p = points[i.index(points.len())];
+                    model.insert(p, v);
+                    segtree.update(p, v);
+                }
+                CompressedOp::Query((xl, xr), (yl, yr)) => {
+                    let contains = |&(x, y): &(i64, i64)| (xl..=xr).contains(&x) && (yl..=yr).contains(&y);
+                    let expected = model.iter().filter(|(p, _)| contains(p)).map(|(_, &v)| v).sum::<i64>();
+                    prop_assert_eq!(expected, segtree.query(xl..=xr, yl..=yr));
+                    prop_assert_eq!(0, segtree.query(xl..xl, ..));
+                }
+                _ => {}
+            }
+        }
+    }
+}